@@ -17,9 +17,9 @@ Feature: Keyboard shortcuts overlay
     And I click the shortcuts close button
     Then the shortcuts overlay should not be visible
 
-  Scenario: Lists all 7 keybindings
+  Scenario: Lists all 8 keybindings
     When I click the shortcuts button
-    Then I should see 7 shortcut rows
+    Then I should see 8 shortcut rows
 
   Scenario: Lists expected shortcut labels
     When I click the shortcuts button
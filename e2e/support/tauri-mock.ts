@@ -165,16 +165,18 @@ export function buildTauriMockScript(opts?: {
 
           // PTY commands
           if (cmd === "pty_create") {
-            const { sessionId, cols, rows, cwd } = args || {};
+            const { sessionId, cols, rows, cwd, onOutput } = args || {};
             window.__TAURI_MOCK_PTY_SESSIONS__.set(sessionId, { cols, rows, cwd });
-            // Simulate initial shell output after a small delay
+            // Simulate initial shell output after a small delay, delivered over
+            // the session's output channel the way the backend does
             setTimeout(() => {
-              const base64 = btoa("user@mock ~ % ");
-              window.__TAURI_MOCK_EMIT__("pty-output-" + sessionId, base64);
+              const bytes = new TextEncoder().encode("user@mock ~ % ");
+              const channel = window["_" + onOutput.id];
+              if (channel) channel({ message: bytes.buffer, index: 0 });
             }, 50);
             return Promise.resolve();
           }
-          if (cmd === "pty_write") {
+          if (cmd === "pty_write" || cmd === "pty_ack") {
             return Promise.resolve();
           }
           if (cmd === "pty_resize") {
@@ -186,11 +188,28 @@ export function buildTauriMockScript(opts?: {
           if (cmd === "pty_destroy") {
             const { sessionId } = args || {};
             window.__TAURI_MOCK_PTY_SESSIONS__.delete(sessionId);
-            return Promise.resolve();
+            return Promise.resolve({ outcome: "hangup", exit: null, elapsed_ms: 0 });
           }
           if (cmd === "pty_get_cwd") {
             return Promise.resolve("/mock/home");
           }
+          if (cmd === "pty_list_profiles") {
+            return Promise.resolve([
+              { id: "default", name: "Default Shell", program: null, args: [], env: {}, cwd: null, login: true },
+            ]);
+          }
+
+          if (cmd === "pty_list_sessions" || cmd === "pty_daemon_errors") {
+            return Promise.resolve([]);
+          }
+
+          // Workspace commands
+          if (cmd === "workspace_restore") {
+            return Promise.resolve(null);
+          }
+          if (cmd === "workspace_set_layout") {
+            return Promise.resolve();
+          }
 
           // File system commands
           if (cmd === "read_file") {
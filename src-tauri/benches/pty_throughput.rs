@@ -0,0 +1,97 @@
+//! Compares the two PTY output paths on a large burst of output:
+//!
+//! - `event+base64`: the old path — every read is base64-encoded and
+//!   JSON-serialized as its own `pty-output-{id}` event payload.
+//! - `channel+raw`: the new path — reads are coalesced by `OutputStream`
+//!   and sent over a `tauri::ipc::Channel` as raw bytes.
+//!
+//! Run with `cargo bench --bench pty_throughput`.
+
+use base64::Engine;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::ipc::{Channel, InvokeResponseBody};
+use terminal_plus_lib::pty::output::{FlowControl, OutputStream};
+
+const TOTAL_BYTES: usize = 64 * 1024 * 1024;
+
+/// Read sizes seen in practice: echo of typing, line-at-a-time logs, full buffers.
+const READ_SIZES: [usize; 3] = [64, 1024, 8192];
+
+struct Stats {
+    elapsed: Duration,
+    messages: usize,
+    ipc_bytes: usize,
+}
+
+fn log_output(len: usize) -> Vec<u8> {
+    let line =
+        b"2025-06-01T12:00:00Z INFO  compiling terminal-plus v0.1.0 (/src/terminal-plus)\r\n";
+    line.iter().copied().cycle().take(len).collect()
+}
+
+fn event_base64(data: &[u8], read_size: usize) -> Stats {
+    let started = Instant::now();
+    let mut messages = 0;
+    let mut ipc_bytes = 0;
+    for chunk in data.chunks(read_size) {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(chunk);
+        // `emit` serializes the payload to JSON before handing it to the webview
+        let payload = serde_json::to_string(&encoded).unwrap();
+        messages += 1;
+        ipc_bytes += payload.len();
+    }
+    Stats {
+        elapsed: started.elapsed(),
+        messages,
+        ipc_bytes,
+    }
+}
+
+fn channel_raw(data: &[u8], read_size: usize) -> Stats {
+    let messages = Arc::new(AtomicUsize::new(0));
+    let ipc_bytes = Arc::new(AtomicUsize::new(0));
+    let (m, b) = (messages.clone(), ipc_bytes.clone());
+    let channel: Channel = Channel::new(move |body| {
+        if let InvokeResponseBody::Raw(bytes) = body {
+            m.fetch_add(1, Ordering::Relaxed);
+            b.fetch_add(bytes.len(), Ordering::Relaxed);
+        }
+        Ok(())
+    });
+
+    let started = Instant::now();
+    // No frontend here to ack, so leave flow control out of the measurement
+    let flow = Arc::new(FlowControl::default());
+    flow.close();
+    let output = OutputStream::new(channel, flow);
+    for chunk in data.chunks(read_size) {
+        output.push(chunk);
+    }
+    drop(output);
+    Stats {
+        elapsed: started.elapsed(),
+        messages: messages.load(Ordering::Relaxed),
+        ipc_bytes: ipc_bytes.load(Ordering::Relaxed),
+    }
+}
+
+fn report(path: &str, read_size: usize, stats: &Stats) {
+    let mib = TOTAL_BYTES as f64 / (1024.0 * 1024.0);
+    println!(
+        "{path:<14} read={read_size:>5}B  {:>8.1} MiB/s  {:>8} msgs  {:>6.1} MiB over IPC",
+        mib / stats.elapsed.as_secs_f64(),
+        stats.messages,
+        stats.ipc_bytes as f64 / (1024.0 * 1024.0),
+    );
+}
+
+fn main() {
+    let data = log_output(TOTAL_BYTES);
+    println!("{} MiB of output per run", TOTAL_BYTES / (1024 * 1024));
+    for read_size in READ_SIZES {
+        report("event+base64", read_size, &event_base64(&data, read_size));
+        report("channel+raw", read_size, &channel_raw(&data, read_size));
+    }
+}
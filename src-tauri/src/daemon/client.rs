@@ -0,0 +1,254 @@
+use parking_lot::Mutex;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use super::protocol::{self, Frame, Reply, Request, SessionEntry, SpawnRequest, PROTOCOL_VERSION};
+use super::socket_path;
+
+/// How long to wait for a daemon we started to begin listening.
+const START_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Largest input frame; bigger writes (pastes) are split.
+const MAX_INPUT_FRAME: usize = 64 * 1024;
+
+/// The app's connection to the session daemon. Requests share one control
+/// connection; each session's data stream and exit wait get their own.
+pub struct DaemonClient {
+    control: Mutex<UnixStream>,
+}
+
+/// A session's data stream: output from the daemon, input to it.
+pub struct DaemonStream {
+    pub pid: Option<u32>,
+    /// Bytes at the start of `reader` that were output before this attach.
+    pub backlog: u64,
+    pub reader: DaemonReader,
+    pub writer: DaemonWriter,
+}
+
+impl DaemonClient {
+    /// Connect to the running daemon, starting one if nothing is listening.
+    /// Fails if the daemon speaks a different protocol version.
+    pub fn connect_or_start() -> Result<Self, String> {
+        let path = socket_path();
+        let control = match UnixStream::connect(&path) {
+            Ok(stream) => stream,
+            Err(_) => {
+                start_daemon()?;
+                wait_for_socket(&path)?
+            }
+        };
+        Ok(DaemonClient {
+            control: Mutex::new(greet(control)?),
+        })
+    }
+
+    fn request(&self, request: &Request) -> Result<Reply, String> {
+        let mut control = self.control.lock();
+        protocol::write_message(&mut *control, request)
+            .and_then(|()| protocol::read_message(&mut *control))
+            .map_err(|e| format!("Session daemon request failed: {e}"))
+    }
+
+    pub fn list(&self) -> Result<Vec<SessionEntry>, String> {
+        match self.request(&Request::List)? {
+            Reply::Sessions { sessions } => Ok(sessions),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn resize(&self, session_id: &str, cols: u16, rows: u16) -> Result<(), String> {
+        let request = Request::Resize {
+            session_id: session_id.to_string(),
+            cols,
+            rows,
+        };
+        match self.request(&request)? {
+            Reply::Ok => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    /// The foreground process group of the session's PTY.
+    pub fn foreground_pgrp(&self, session_id: &str) -> Option<i32> {
+        let request = Request::ForegroundPgrp {
+            session_id: session_id.to_string(),
+        };
+        match self.request(&request) {
+            Ok(Reply::Pgrp { pgrp }) => pgrp,
+            _ => None,
+        }
+    }
+
+    /// Tell the daemon the session is gone for good.
+    pub fn close(&self, session_id: &str) {
+        let _ = self.request(&Request::Close {
+            session_id: session_id.to_string(),
+        });
+    }
+
+    /// Start a program in the daemon and stream its session.
+    pub fn spawn(&self, request: SpawnRequest) -> Result<DaemonStream, String> {
+        open_stream(&Request::Spawn(request))
+    }
+
+    /// Stream a running session, starting with the output the daemon kept.
+    pub fn attach(&self, session_id: &str) -> Result<DaemonStream, String> {
+        open_stream(&Request::Attach {
+            session_id: session_id.to_string(),
+        })
+    }
+
+    /// Block until the session's program exits, returning `(exit_code, signal)`.
+    /// Gives up with `(None, None)` if the daemon goes away.
+    pub fn wait(&self, session_id: &str) -> (Option<i32>, Option<i32>) {
+        let request = Request::Wait {
+            session_id: session_id.to_string(),
+        };
+        let reply = connect().and_then(|mut stream| {
+            protocol::write_message(&mut stream, &request)
+                .and_then(|()| protocol::read_message(&mut stream))
+                .map_err(|e| e.to_string())
+        });
+        match reply {
+            Ok(Reply::Exit { exit_code, signal }) => (exit_code, signal),
+            _ => (None, None),
+        }
+    }
+}
+
+fn connect() -> Result<UnixStream, String> {
+    let stream = UnixStream::connect(socket_path())
+        .map_err(|e| format!("Failed to connect to the session daemon: {e}"))?;
+    greet(stream)
+}
+
+fn greet(mut stream: UnixStream) -> Result<UnixStream, String> {
+    let hello = Request::Hello {
+        version: PROTOCOL_VERSION,
+    };
+    let reply = protocol::write_message(&mut stream, &hello)
+        .and_then(|()| protocol::read_message(&mut stream))
+        .map_err(|e| format!("Session daemon handshake failed: {e}"))?;
+    match reply {
+        Reply::Hello { version } if version == PROTOCOL_VERSION => Ok(stream),
+        reply => Err(unexpected(reply)),
+    }
+}
+
+fn open_stream(request: &Request) -> Result<DaemonStream, String> {
+    let mut stream = connect()?;
+    protocol::write_message(&mut stream, request)
+        .map_err(|e| format!("Session daemon request failed: {e}"))?;
+    let (pid, backlog) = match protocol::read_message(&mut stream) {
+        Ok(Reply::Spawned { pid }) => (pid, 0),
+        Ok(Reply::Attached { pid, backlog }) => (pid, backlog),
+        Ok(reply) => return Err(unexpected(reply)),
+        Err(e) => return Err(format!("Session daemon request failed: {e}")),
+    };
+    let writer = stream
+        .try_clone()
+        .map_err(|e| format!("Failed to clone the daemon connection: {e}"))?;
+    Ok(DaemonStream {
+        pid,
+        backlog,
+        reader: DaemonReader {
+            stream,
+            pending: Vec::new(),
+            pos: 0,
+        },
+        writer: DaemonWriter { stream: writer },
+    })
+}
+
+fn unexpected(reply: Reply) -> String {
+    match reply {
+        Reply::Error { message } => message,
+        reply => format!("Unexpected reply from the session daemon: {reply:?}"),
+    }
+}
+
+/// Run `terminal-plus --daemon` detached from the app, in its own session so
+/// quitting the app (or closing the terminal it was started from) leaves it running.
+fn start_daemon() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to find the app binary: {e}"))?;
+    let mut cmd = Command::new(exe);
+    cmd.arg("--daemon")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    unsafe {
+        cmd.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| format!("Failed to start the session daemon: {e}"))?;
+    // Reap it whenever it exits, so it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn wait_for_socket(path: &Path) -> Result<UnixStream, String> {
+    let started = Instant::now();
+    loop {
+        match UnixStream::connect(path) {
+            Ok(stream) => return Ok(stream),
+            Err(e) if started.elapsed() >= START_TIMEOUT => {
+                return Err(format!("The session daemon didn't start: {e}"));
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+/// Session output, unwrapped from the daemon's frames. EOF once the PTY has
+/// closed or the daemon is gone.
+pub struct DaemonReader {
+    stream: UnixStream,
+    /// The rest of a frame bigger than the caller's buffer.
+    pending: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for DaemonReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.pending.len() {
+            match protocol::read_frame::<Reply>(&mut self.stream)? {
+                Some(Frame::Data(data)) => {
+                    self.pending = data;
+                    self.pos = 0;
+                }
+                Some(Frame::Message(_)) => {}
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.pending.len() - self.pos);
+        buf[..n].copy_from_slice(&self.pending[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Session input, wrapped in data frames.
+pub struct DaemonWriter {
+    stream: UnixStream,
+}
+
+impl Write for DaemonWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(MAX_INPUT_FRAME);
+        protocol::write_data(&mut self.stream, &buf[..n])?;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
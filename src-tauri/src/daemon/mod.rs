@@ -0,0 +1,32 @@
+//! The session daemon: `terminal-plus --daemon`, a background process that owns
+//! the PTYs so shells outlive the app. The app talks to it over a Unix socket
+//! (see [`protocol`]) and keeps all parsing, history and events on its side.
+
+pub mod client;
+pub mod protocol;
+pub mod server;
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::settings::{self, terminal_plus_dir};
+
+/// The `daemon` section of `~/.terminal-plus/settings.json`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// Run sessions in the daemon instead of in the app process.
+    pub enabled: bool,
+}
+
+impl DaemonConfig {
+    /// Load the config, using the defaults when the settings file doesn't exist.
+    pub fn load() -> Result<Self, String> {
+        settings::load_section("daemon")
+    }
+}
+
+/// Where the daemon listens.
+pub fn socket_path() -> PathBuf {
+    terminal_plus_dir().join("daemon.sock")
+}
@@ -0,0 +1,165 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+/// Bumped whenever a message changes shape. The daemon refuses clients that
+/// speak another version, and the app then runs its sessions in-process.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Largest frame either side accepts, so a corrupt length can't allocate gigabytes.
+const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+/// Frame kinds: raw PTY bytes, or a JSON [`Request`] / [`Reply`].
+const KIND_DATA: u8 = 0;
+const KIND_MESSAGE: u8 = 1;
+
+/// Sent by the app. Every connection starts with `Hello`; `Spawn` and `Attach`
+/// then turn it into the session's data stream, `Wait` blocks until the
+/// program exits, and anything else is a request/reply pair.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Hello {
+        version: u32,
+    },
+    /// Start a program on a new PTY, replacing any session with the same id.
+    Spawn(SpawnRequest),
+    /// Stream an existing session: its backlog first, then live output.
+    Attach {
+        session_id: String,
+    },
+    List,
+    Resize {
+        session_id: String,
+        cols: u16,
+        rows: u16,
+    },
+    ForegroundPgrp {
+        session_id: String,
+    },
+    Wait {
+        session_id: String,
+    },
+    /// Forget a session the app has torn down.
+    Close {
+        session_id: String,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpawnRequest {
+    pub session_id: String,
+    pub argv: Vec<String>,
+    /// Variables set on top of the daemon's own environment.
+    pub env: Vec<(String, String)>,
+    pub cwd: Option<String>,
+    pub cols: u16,
+    pub rows: u16,
+    /// Stored for the app and handed back by `List`; the daemon doesn't read it.
+    pub meta: serde_json::Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reply {
+    Hello {
+        version: u32,
+    },
+    Ok,
+    Spawned {
+        pid: Option<u32>,
+    },
+    Attached {
+        pid: Option<u32>,
+        /// Length of the backlog: output from before this attach, which a
+        /// previous run of the app has already seen.
+        backlog: u64,
+    },
+    Sessions {
+        sessions: Vec<SessionEntry>,
+    },
+    Pgrp {
+        pgrp: Option<i32>,
+    },
+    Exit {
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A session as listed by the daemon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionEntry {
+    pub session_id: String,
+    pub pid: Option<u32>,
+    pub cols: u16,
+    pub rows: u16,
+    pub meta: serde_json::Value,
+}
+
+pub enum Frame<T> {
+    Data(Vec<u8>),
+    Message(T),
+}
+
+pub fn write_data(w: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    write_frame(w, KIND_DATA, data)
+}
+
+pub fn write_message(w: &mut impl Write, message: &impl Serialize) -> io::Result<()> {
+    let json = serde_json::to_vec(message).map_err(io::Error::other)?;
+    write_frame(w, KIND_MESSAGE, &json)
+}
+
+fn write_frame(w: &mut impl Write, kind: u8, payload: &[u8]) -> io::Result<()> {
+    let mut header = [0u8; 5];
+    header[0] = kind;
+    header[1..].copy_from_slice(&(payload.len() as u32).to_be_bytes());
+    w.write_all(&header)?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// The next frame, or `None` once the peer has closed the connection.
+pub fn read_frame<T: DeserializeOwned>(r: &mut impl Read) -> io::Result<Option<Frame<T>>> {
+    let mut header = [0u8; 5];
+    match r.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Frame of {len} bytes is too large"),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    match header[0] {
+        KIND_DATA => Ok(Some(Frame::Data(payload))),
+        KIND_MESSAGE => serde_json::from_slice(&payload)
+            .map(|message| Some(Frame::Message(message)))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        kind => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unknown frame kind {kind}"),
+        )),
+    }
+}
+
+/// The next frame, which must be a message.
+pub fn read_message<T: DeserializeOwned>(r: &mut impl Read) -> io::Result<T> {
+    match read_frame(r)? {
+        Some(Frame::Message(message)) => Ok(message),
+        Some(Frame::Data(_)) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Expected a message, got data",
+        )),
+        None => Err(io::ErrorKind::UnexpectedEof.into()),
+    }
+}
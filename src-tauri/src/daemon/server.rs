@@ -0,0 +1,523 @@
+use parking_lot::{Condvar, Mutex};
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::protocol::{self, Frame, Reply, Request, SessionEntry, SpawnRequest, PROTOCOL_VERSION};
+use super::socket_path;
+use crate::pty::history::OutputHistory;
+use crate::pty::session::wait_for_exit;
+
+/// Output kept per session for an app that (re)attaches.
+const BACKLOG: usize = 1024 * 1024;
+
+/// How long a freshly started daemon waits for its first client.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+type ExitStatus = (Option<i32>, Option<i32>);
+
+struct Daemon {
+    state: Mutex<DaemonState>,
+    /// Signalled when a session or connection goes away.
+    changed: Condvar,
+    next_attachment: AtomicU64,
+}
+
+#[derive(Default)]
+struct DaemonState {
+    sessions: HashMap<String, Arc<Session>>,
+    connections: usize,
+    /// Whether any client has connected yet.
+    served: bool,
+}
+
+struct Session {
+    id: String,
+    pid: Option<u32>,
+    meta: serde_json::Value,
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    output: Mutex<Output>,
+    exit: Mutex<Option<ExitStatus>>,
+    exited: Condvar,
+}
+
+struct Output {
+    backlog: OutputHistory,
+    /// The app connection output currently goes to.
+    attached: Option<Attachment>,
+    /// The PTY has reached EOF; attachments are closed once the backlog is sent.
+    eof: bool,
+}
+
+struct Attachment {
+    id: u64,
+    stream: UnixStream,
+}
+
+/// Run the daemon until it has no sessions and no clients left. Nobody reads
+/// its output; an app waiting for it gives up after a while instead.
+pub fn run() {
+    if serve(&socket_path()).is_err() {
+        std::process::exit(1);
+    }
+}
+
+fn serve(path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    }
+    // Held until the daemon exits: when two apps start a daemon at once, the
+    // second must not remove the first one's socket
+    let _lock = lock(&path.with_extension("lock"))?;
+    if UnixStream::connect(path).is_ok() {
+        return Err(format!("Another daemon is listening on {}", path.display()));
+    }
+    // Left behind by a daemon that didn't shut down cleanly
+    let _ = std::fs::remove_file(path);
+    // Created owner-only, rather than restricted after the fact: another user
+    // connecting in between could spawn programs as this one
+    let umask = unsafe { libc::umask(0o077) };
+    let listener = UnixListener::bind(path);
+    unsafe { libc::umask(umask) };
+    let listener = listener.map_err(|e| format!("Failed to listen on {}: {e}", path.display()))?;
+
+    let daemon = Arc::new(Daemon {
+        state: Mutex::new(DaemonState::default()),
+        changed: Condvar::new(),
+        next_attachment: AtomicU64::new(0),
+    });
+    let acceptor = daemon.clone();
+    std::thread::spawn(move || {
+        let uid = unsafe { libc::getuid() };
+        for stream in listener.incoming().flatten() {
+            if peer_uid(&stream) != Some(uid) {
+                continue;
+            }
+            let mut state = acceptor.state.lock();
+            state.connections += 1;
+            state.served = true;
+            drop(state);
+            let daemon = acceptor.clone();
+            std::thread::spawn(move || {
+                daemon.handle(stream);
+                daemon.state.lock().connections -= 1;
+                daemon.changed.notify_all();
+            });
+        }
+    });
+
+    let started = Instant::now();
+    let mut state = daemon.state.lock();
+    loop {
+        let idle = state.sessions.is_empty() && state.connections == 0;
+        if idle && (state.served || started.elapsed() >= STARTUP_TIMEOUT) {
+            break;
+        }
+        daemon.changed.wait_for(&mut state, Duration::from_secs(1));
+    }
+    drop(state);
+    let _ = std::fs::remove_file(path);
+    Ok(())
+}
+
+/// The user id of the process on the other end of `stream`.
+#[cfg(target_os = "linux")]
+fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let ok = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            (&mut cred as *mut libc::ucred).cast(),
+            &mut len,
+        )
+    } == 0;
+    ok.then_some(cred.uid)
+}
+
+/// The user id of the process on the other end of `stream`.
+#[cfg(not(target_os = "linux"))]
+fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let mut uid = 0;
+    let mut gid = 0;
+    let ok = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } == 0;
+    ok.then_some(uid)
+}
+
+/// Take an exclusive lock on `path`, failing if another daemon holds it.
+fn lock(path: &Path) -> Result<File, String> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        return Err(format!("Another daemon holds {}", path.display()));
+    }
+    Ok(file)
+}
+
+impl Daemon {
+    fn handle(self: &Arc<Self>, mut stream: UnixStream) {
+        match protocol::read_message(&mut stream) {
+            Ok(Request::Hello { version }) if version == PROTOCOL_VERSION => {
+                let reply = Reply::Hello {
+                    version: PROTOCOL_VERSION,
+                };
+                if protocol::write_message(&mut stream, &reply).is_err() {
+                    return;
+                }
+            }
+            Ok(Request::Hello { version }) => {
+                let message =
+                    format!("The daemon speaks protocol version {PROTOCOL_VERSION}, not {version}");
+                let _ = protocol::write_message(&mut stream, &Reply::Error { message });
+                return;
+            }
+            _ => return,
+        }
+
+        loop {
+            let request = match protocol::read_message::<Request>(&mut stream) {
+                Ok(request) => request,
+                Err(_) => return,
+            };
+            let reply = match request {
+                Request::Spawn(request) => match self.spawn(request) {
+                    Ok(session) => {
+                        let pid = session.pid;
+                        return self.stream_session(&session, stream, |_| Reply::Spawned { pid });
+                    }
+                    Err(message) => Reply::Error { message },
+                },
+                Request::Attach { session_id } => match self.session(&session_id) {
+                    Ok(session) => {
+                        let pid = session.pid;
+                        let reply = |backlog| Reply::Attached { pid, backlog };
+                        return self.stream_session(&session, stream, reply);
+                    }
+                    Err(message) => Reply::Error { message },
+                },
+                Request::Wait { session_id } => match self.session(&session_id) {
+                    Ok(session) => {
+                        let (exit_code, signal) = session.wait();
+                        Reply::Exit { exit_code, signal }
+                    }
+                    Err(message) => Reply::Error { message },
+                },
+                Request::List => Reply::Sessions {
+                    sessions: self.list(),
+                },
+                Request::Resize {
+                    session_id,
+                    cols,
+                    rows,
+                } => match self.session(&session_id).and_then(|s| s.resize(cols, rows)) {
+                    Ok(()) => Reply::Ok,
+                    Err(message) => Reply::Error { message },
+                },
+                Request::ForegroundPgrp { session_id } => match self.session(&session_id) {
+                    Ok(session) => Reply::Pgrp {
+                        pgrp: session.master.lock().process_group_leader(),
+                    },
+                    Err(message) => Reply::Error { message },
+                },
+                Request::Close { session_id } => {
+                    self.remove(&session_id, None);
+                    Reply::Ok
+                }
+                Request::Hello { .. } => Reply::Error {
+                    message: "Already greeted".to_string(),
+                },
+            };
+            if protocol::write_message(&mut stream, &reply).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn session(&self, session_id: &str) -> Result<Arc<Session>, String> {
+        self.state
+            .lock()
+            .sessions
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| format!("Session {session_id} not found"))
+    }
+
+    fn list(&self) -> Vec<SessionEntry> {
+        let sessions: Vec<_> = self.state.lock().sessions.values().cloned().collect();
+        sessions
+            .iter()
+            .map(|session| {
+                let size = session.master.lock().get_size().unwrap_or_default();
+                SessionEntry {
+                    session_id: session.id.clone(),
+                    pid: session.pid,
+                    cols: size.cols,
+                    rows: size.rows,
+                    meta: session.meta.clone(),
+                }
+            })
+            .collect()
+    }
+
+    fn spawn(self: &Arc<Self>, request: SpawnRequest) -> Result<Arc<Session>, String> {
+        let SpawnRequest {
+            session_id,
+            argv,
+            env,
+            cwd,
+            cols,
+            rows,
+            meta,
+        } = request;
+        if argv.is_empty() {
+            return Err("Nothing to run".to_string());
+        }
+        self.check_replaceable(&session_id)?;
+        let pair = native_pty_system()
+            .openpty(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| format!("Failed to open PTY: {e}"))?;
+        let mut cmd = CommandBuilder::from_argv(argv.into_iter().map(Into::into).collect());
+        for (key, val) in env {
+            cmd.env(key, val);
+        }
+        if let Some(cwd) = cwd {
+            cmd.cwd(cwd);
+        }
+        let mut child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| format!("Failed to spawn shell: {e}"))?;
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| format!("Failed to get PTY writer: {e}"))?;
+        let reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| format!("Failed to get PTY reader: {e}"))?;
+
+        let session = Arc::new(Session {
+            id: session_id.clone(),
+            pid: child.process_id(),
+            meta,
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            output: Mutex::new(Output {
+                backlog: OutputHistory::new(BACKLOG),
+                attached: None,
+                eof: false,
+            }),
+            exit: Mutex::new(None),
+            exited: Condvar::new(),
+        });
+        // A restarted program takes over its session id, unless a running
+        // one took it while this one started
+        let mut state = self.state.lock();
+        if let Err(e) = replaceable(&state, &session_id) {
+            drop(state);
+            let _ = child.kill();
+            let _ = wait_for_exit(&mut child);
+            return Err(e);
+        }
+        let replaced = state.sessions.insert(session_id, session.clone());
+        drop(state);
+        if let Some(replaced) = replaced {
+            replaced.detach_all();
+        }
+
+        let waiter = session.clone();
+        let daemon = self.clone();
+        std::thread::spawn(move || {
+            let status = wait_for_exit(&mut child);
+            *waiter.exit.lock() = Some(status);
+            waiter.exited.notify_all();
+            daemon.remove_if_done(&waiter);
+        });
+
+        let pump = session.clone();
+        std::thread::spawn(move || pump.pump(reader));
+        Ok(session)
+    }
+
+    fn check_replaceable(&self, session_id: &str) -> Result<(), String> {
+        replaceable(&self.state.lock(), session_id)
+    }
+
+    /// Make `stream` the session's data stream: the reply (given the backlog's
+    /// length) and backlog, then live output one way and input the other,
+    /// until either side closes.
+    fn stream_session(
+        &self,
+        session: &Arc<Session>,
+        stream: UnixStream,
+        reply: impl FnOnce(u64) -> Reply,
+    ) {
+        let Ok(mut input) = stream.try_clone() else {
+            return;
+        };
+        let id = self.next_attachment.fetch_add(1, Ordering::Relaxed);
+        if session.attach(Attachment { id, stream }, reply).is_err() {
+            return;
+        }
+        while let Ok(Some(frame)) = protocol::read_frame::<Request>(&mut input) {
+            if let Frame::Data(data) = frame {
+                let mut writer = session.writer.lock();
+                if writer
+                    .write_all(&data)
+                    .and_then(|()| writer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+        session.detach(id);
+        self.remove_if_done(session);
+    }
+
+    /// Drop a session once its program has exited and no app is showing it.
+    fn remove_if_done(&self, session: &Arc<Session>) {
+        let exited = session.exit.lock().is_some();
+        if exited && session.output.lock().attached.is_none() {
+            self.remove(&session.id, Some(session));
+        }
+    }
+
+    /// Forget a session, or only the given instance of it.
+    fn remove(&self, session_id: &str, only: Option<&Arc<Session>>) {
+        let mut state = self.state.lock();
+        let matches = match (state.sessions.get(session_id), only) {
+            (Some(current), Some(only)) => Arc::ptr_eq(current, only),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        // Dropped outside the lock: closing the master hangs up a running program
+        let removed = if matches {
+            state.sessions.remove(session_id)
+        } else {
+            None
+        };
+        drop(state);
+        if let Some(session) = removed {
+            session.detach_all();
+        }
+        self.changed.notify_all();
+    }
+}
+
+/// A session id can only be reused once its program has exited (a restart);
+/// a running one would be left without an id to list, attach to or close.
+fn replaceable(state: &DaemonState, session_id: &str) -> Result<(), String> {
+    match state.sessions.get(session_id) {
+        Some(session) if session.exit.lock().is_none() => {
+            Err(format!("Session {session_id} is still running"))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Session {
+    /// Copy PTY output into the backlog and to the attached app.
+    fn pump(&self, mut reader: Box<dyn Read + Send>) {
+        let mut buf = [0u8; 8192];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            let mut output = self.output.lock();
+            output.backlog.push(&buf[..n]);
+            if let Some(attachment) = &mut output.attached {
+                if protocol::write_data(&mut attachment.stream, &buf[..n]).is_err() {
+                    output.attached = None;
+                }
+            }
+        }
+        let mut output = self.output.lock();
+        output.eof = true;
+        if let Some(attachment) = &output.attached {
+            let _ = attachment.stream.shutdown(Shutdown::Write);
+        }
+    }
+
+    fn attach(
+        &self,
+        mut attachment: Attachment,
+        reply: impl FnOnce(u64) -> Reply,
+    ) -> std::io::Result<()> {
+        let mut output = self.output.lock();
+        let backlog = output.backlog.tail_from_line(BACKLOG);
+        protocol::write_message(&mut attachment.stream, &reply(backlog.len() as u64))?;
+        if !backlog.is_empty() {
+            protocol::write_data(&mut attachment.stream, &backlog)?;
+        }
+        if output.eof {
+            let _ = attachment.stream.shutdown(Shutdown::Write);
+        }
+        // A previous app connection stops receiving output
+        if let Some(previous) = output.attached.replace(attachment) {
+            let _ = previous.stream.shutdown(Shutdown::Both);
+        }
+        Ok(())
+    }
+
+    fn detach(&self, id: u64) {
+        let mut output = self.output.lock();
+        if output.attached.as_ref().is_some_and(|a| a.id == id) {
+            output.attached = None;
+        }
+    }
+
+    fn detach_all(&self) {
+        if let Some(attachment) = self.output.lock().attached.take() {
+            let _ = attachment.stream.shutdown(Shutdown::Both);
+        }
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+        self.master
+            .lock()
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| format!("PTY resize failed: {e}"))
+    }
+
+    fn wait(&self) -> ExitStatus {
+        let mut exit = self.exit.lock();
+        loop {
+            if let Some(status) = *exit {
+                return status;
+            }
+            self.exited.wait(&mut exit);
+        }
+    }
+}
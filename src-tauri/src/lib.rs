@@ -1,30 +1,81 @@
 mod commands;
+#[cfg(unix)]
+pub mod daemon;
 mod git;
-mod pty;
+pub mod pty;
+mod settings;
+mod tasks;
+mod tmux;
+mod workspace;
 
 use pty::manager::PtyManager;
+use tauri::{Manager, RunEvent};
+use workspace::WorkspaceState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(PtyManager::new())
+        .manage(WorkspaceState::new())
+        .setup(|app| {
+            #[cfg(unix)]
+            app.state::<PtyManager>().connect_daemon(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             pty::commands::pty_create,
             pty::commands::pty_write,
+            pty::commands::pty_write_group,
+            pty::commands::pty_join_group,
+            pty::commands::pty_leave_group,
+            pty::commands::pty_ack,
             pty::commands::pty_resize,
             pty::commands::pty_destroy,
             pty::commands::pty_get_cwd,
+            pty::commands::pty_list_profiles,
+            pty::commands::pty_list_sessions,
+            pty::commands::pty_daemon_errors,
+            pty::commands::pty_attach,
+            pty::commands::pty_get_foreground_process,
+            pty::commands::pty_list_blocks,
+            pty::commands::pty_get_block_output,
+            pty::commands::pty_start_recording,
+            pty::commands::pty_stop_recording,
+            pty::commands::pty_replay_open,
+            pty::commands::pty_replay_pause,
+            pty::commands::pty_replay_resume,
+            pty::commands::pty_replay_seek,
+            pty::commands::pty_replay_set_speed,
+            pty::commands::pty_get_screen_text,
+            pty::commands::pty_search,
+            pty::commands::pty_set_notifications_muted,
+            pty::commands::pty_set_monitor,
+            pty::commands::pty_set_prompt_palette,
             git::commands::git_log,
             git::commands::git_is_repo,
             git::commands::git_commit_files,
             git::commands::git_file_diff,
             git::commands::git_local_changes,
             git::commands::git_local_file_diff,
+            tasks::commands::task_list,
+            tasks::commands::task_spawn,
+            workspace::commands::workspace_set_layout,
+            workspace::commands::workspace_restore,
+            workspace::commands::workspace_open_layout,
             commands::read_file,
             commands::write_file,
             commands::list_dir,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let RunEvent::Exit = event {
+                // The layout was saved as it changed, so a failure here only
+                // loses the panes' cwd and scrollback
+                let manager = app.state::<PtyManager>();
+                let _ = app.state::<WorkspaceState>().save(&manager);
+            }
+        });
 }
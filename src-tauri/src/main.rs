@@ -2,5 +2,10 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // The session daemon runs from the same binary, without the UI
+    #[cfg(unix)]
+    if std::env::args().nth(1).as_deref() == Some("--daemon") {
+        return terminal_plus_lib::daemon::server::run();
+    }
     terminal_plus_lib::run()
 }
@@ -0,0 +1,94 @@
+use regex::Regex;
+use std::time::Duration;
+use tauri::ipc::Channel;
+
+use super::replay::ReplayControl;
+use super::types::{
+    BlockOutput, CommandBlock, ForegroundProcess, LineRange, PtyTeardown, RecordingInfo,
+    ReplayState, ScreenText, SearchResults, SessionInfo,
+};
+
+/// A session the manager can drive from the frontend: a shell on a real PTY,
+/// or something that only looks like one (e.g. a replayed recording).
+///
+/// Output goes to the `onOutput` channel the session was opened with, or the
+/// one it was last attached to.
+/// Optional capabilities default to "not supported".
+pub trait SessionBackend: Send + Sync {
+    fn write(&self, data: &[u8]) -> Result<(), String>;
+
+    /// The frontend has rendered `bytes` more bytes of output.
+    fn ack(&self, bytes: usize);
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<(), String>;
+
+    /// Stop the session, blocking for at most about `grace`.
+    fn shutdown(&self, grace: Duration) -> PtyTeardown;
+
+    fn get_cwd(&self) -> String;
+
+    /// What `pty_list_sessions` reports; `None` keeps the session unlisted.
+    fn info(&self) -> Option<SessionInfo> {
+        None
+    }
+
+    /// Send output to `on_output` from now on, replaying recent output first.
+    fn attach(&self, _on_output: Channel) -> Result<(), String> {
+        Err("This session can't be reattached".to_string())
+    }
+
+    fn foreground_process(&self) -> Option<ForegroundProcess> {
+        None
+    }
+
+    fn blocks(&self) -> Vec<CommandBlock> {
+        Vec::new()
+    }
+
+    fn block_output(&self, _block_id: u64) -> Option<BlockOutput> {
+        None
+    }
+
+    /// Text of the backend's screen model; `None` means the visible screen.
+    fn screen_text(&self, _range: Option<LineRange>, _styled: bool) -> Result<ScreenText, String> {
+        Err("This session has no screen model".to_string())
+    }
+
+    /// The newest `limit` lines of the session's history matching `regex`.
+    fn search(&self, _regex: &Regex, _limit: usize) -> Result<SearchResults, String> {
+        Err("This session has no history to search".to_string())
+    }
+
+    fn start_recording(&self, _path: String) -> Result<RecordingInfo, String> {
+        Err("This session can't be recorded".to_string())
+    }
+
+    fn stop_recording(&self) -> Option<RecordingInfo> {
+        None
+    }
+
+    /// Stop (or resume) announcing bells and OSC 9/777 notifications.
+    fn set_notifications_muted(&self, _muted: bool) -> Result<(), String> {
+        Err("This session doesn't raise notifications".to_string())
+    }
+
+    /// Watch for output after `activity_after` of quiet and for `silence_after`
+    /// without output; `None` turns a monitor off.
+    fn set_monitor(
+        &self,
+        _activity_after: Option<Duration>,
+        _silence_after: Option<Duration>,
+    ) -> Result<(), String> {
+        Err("This session can't be monitored".to_string())
+    }
+
+    /// Block until the shell has drawn a prompt, for at most `timeout`. False
+    /// if it hasn't, including for shells that don't mark their prompts.
+    fn wait_for_prompt(&self, _timeout: Duration) -> bool {
+        false
+    }
+
+    fn control_replay(&self, _control: ReplayControl) -> Result<ReplayState, String> {
+        Err("Not a replay session".to_string())
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::osc::{self, Osc};
+use super::types::CommandBlock;
+
+/// Oldest blocks are forgotten beyond this many per session.
+const MAX_BLOCKS: usize = 1000;
+
+/// Builds the list of command blocks from OSC 133 (FinalTerm) marks:
+///
+/// - `A` — prompt starts
+/// - `B` — prompt ends, the user is typing
+/// - `C[;cmdline_url=<command>]` — command starts, output follows
+/// - `D[;<exit code>]` — command finished
+#[derive(Default)]
+pub struct BlockTracker {
+    blocks: VecDeque<CommandBlock>,
+    next_id: u64,
+}
+
+impl BlockTracker {
+    /// Apply an OSC 133 mark. `cwd` is the directory last reported by the shell.
+    pub fn apply(&mut self, mark: &Osc, cwd: Option<&str>) {
+        let mut params = mark.data.split(';');
+        match params.next() {
+            // A new prompt without a `D` means the command never reported back
+            // (e.g. the shell was replaced with `exec`)
+            Some("A") => self.finish(mark.start, None),
+            Some("C") => {
+                self.finish(mark.start, None);
+                let command = params
+                    .find_map(|p| p.strip_prefix("cmdline_url="))
+                    .map(osc::percent_decode);
+                self.next_id += 1;
+                self.blocks.push_back(CommandBlock {
+                    id: self.next_id,
+                    command,
+                    cwd: cwd.map(str::to_string),
+                    started_at: now_ms(),
+                    finished_at: None,
+                    exit_code: None,
+                    output_start: mark.end,
+                    output_end: None,
+                });
+                if self.blocks.len() > MAX_BLOCKS {
+                    self.blocks.pop_front();
+                }
+            }
+            Some("D") => {
+                let exit_code = params.next().and_then(|code| code.parse().ok());
+                self.finish(mark.start, exit_code);
+            }
+            _ => {}
+        }
+    }
+
+    /// Close the running block, if any, with its output ending at `end`.
+    fn finish(&mut self, end: u64, exit_code: Option<i32>) {
+        if let Some(block) = self.blocks.back_mut() {
+            if block.finished_at.is_none() {
+                block.finished_at = Some(now_ms());
+                block.exit_code = exit_code;
+                block.output_end = Some(end);
+            }
+        }
+    }
+
+    pub fn list(&self) -> Vec<CommandBlock> {
+        self.blocks.iter().cloned().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<CommandBlock> {
+        self.blocks.iter().find(|b| b.id == id).cloned()
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Reduce raw terminal output to the plain text a user would copy:
+/// escape sequences are dropped, `\r` rewinds the line (progress bars keep
+/// only their final state) and backspace erases.
+pub fn strip_escapes(raw: &[u8]) -> String {
+    let text = String::from_utf8_lossy(raw);
+    let mut out = String::with_capacity(text.len());
+    let mut line_start = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters, then a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                }
+                // OSC / DCS / SOS / PM / APC: until BEL or ST
+                Some(']' | 'P' | 'X' | '^' | '_') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                // Charset designation takes one more character
+                Some('(' | ')' | '*' | '+') => {
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\r' => {
+                if chars.peek() != Some(&'\n') {
+                    out.truncate(line_start);
+                }
+            }
+            '\n' => {
+                out.push('\n');
+                line_start = out.len();
+            }
+            '\x08' => {
+                if out.len() > line_start {
+                    out.pop();
+                }
+            }
+            '\t' => out.push('\t'),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
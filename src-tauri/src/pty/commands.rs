@@ -1,9 +1,25 @@
-use base64::Engine;
-use tauri::{AppHandle, State};
+use std::time::Duration;
+use tauri::ipc::{Channel, InvokeBody, Request};
+use tauri::{AppHandle, Emitter, Manager, State};
 
 use super::manager::PtyManager;
+use super::monitor;
+use super::osc;
+use super::palette::{self, PromptPalette};
+use super::profile::{self, ShellProfile};
+use super::recording;
+use super::replay::ReplayControl;
+use super::scrollback::{ScrollbackConfig, DEFAULT_SEARCH_LIMIT};
+use super::session::{ExitPolicy, SpawnOptions, DEFAULT_GRACE_MS};
+use super::types::{
+    BlockOutput, CommandBlock, ForegroundProcess, GroupMembership, LineRange, PtyTeardown,
+    RecordingInfo, ReplayState, ScreenText, SearchResults, SessionInfo, GROUP_CHANGED_EVENT,
+};
 
+/// Start a session: the profile's shell, or with `program` a command pane that
+/// runs it directly with `args`, without shell integration.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn pty_create(
     app: AppHandle,
     state: State<'_, PtyManager>,
@@ -11,20 +27,119 @@ pub fn pty_create(
     cols: u16,
     rows: u16,
     cwd: Option<String>,
+    profile_id: Option<String>,
+    program: Option<String>,
+    args: Option<Vec<String>>,
+    on_exit: Option<ExitPolicy>,
+    on_output: Channel,
 ) -> Result<(), String> {
-    state.create_session(app, session_id, cols, rows, cwd)
+    let profile = match program {
+        Some(program) => ShellProfile::command(program, args.unwrap_or_default()),
+        None => profile::find_profile(profile_id.as_deref())?,
+    };
+    let options = SpawnOptions {
+        cols,
+        rows,
+        cwd,
+        profile,
+        scrollback: ScrollbackConfig::load()?,
+        on_exit: on_exit.unwrap_or_default(),
+    };
+    state.create_session(app, session_id, options, on_output)
 }
 
 #[tauri::command]
-pub fn pty_write(
+pub fn pty_list_profiles() -> Result<Vec<ShellProfile>, String> {
+    profile::load_profiles()
+}
+
+/// Sessions still running in the backend, e.g. after the window was reloaded.
+#[tauri::command]
+pub fn pty_list_sessions(state: State<'_, PtyManager>) -> Vec<SessionInfo> {
+    state.list_sessions()
+}
+
+/// Problems with the session daemon since launch: why it's not in use, or
+/// which of its sessions couldn't be taken over.
+#[tauri::command]
+pub fn pty_daemon_errors(state: State<'_, PtyManager>) -> Vec<String> {
+    state.daemon_errors()
+}
+
+/// Reconnect to a running session. Recent output is replayed on `on_output`
+/// before live output continues there.
+#[tauri::command]
+pub fn pty_attach(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    on_output: Channel,
+) -> Result<(), String> {
+    state.attach_session(&session_id, on_output)
+}
+
+/// Write input to a session. The body is the raw bytes; the target session
+/// travels in the `session-id` header.
+#[tauri::command]
+pub fn pty_write(state: State<'_, PtyManager>, request: Request<'_>) -> Result<(), String> {
+    let session_id = request
+        .headers()
+        .get("session-id")
+        .and_then(|v| v.to_str().ok())
+        .ok_or("Missing session-id header")?;
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err("pty_write expects a raw byte body".to_string());
+    };
+    state.write_to_session(session_id, data)
+}
+
+/// Write input to every session in a broadcast group. The body is the raw
+/// bytes; the group name travels percent-encoded in the `group` header.
+#[tauri::command]
+pub fn pty_write_group(state: State<'_, PtyManager>, request: Request<'_>) -> Result<(), String> {
+    let group = request
+        .headers()
+        .get("group")
+        .and_then(|v| v.to_str().ok())
+        .map(osc::percent_decode)
+        .ok_or("Missing group header")?;
+    let InvokeBody::Raw(data) = request.body() else {
+        return Err("pty_write_group expects a raw byte body".to_string());
+    };
+    state.write_to_group(&group, data)
+}
+
+#[tauri::command]
+pub fn pty_join_group(
+    app: AppHandle,
+    state: State<'_, PtyManager>,
+    session_id: String,
+    group: String,
+) -> Result<GroupMembership, String> {
+    let membership = state.join_group(&session_id, &group)?;
+    let _ = app.emit(GROUP_CHANGED_EVENT, &membership);
+    Ok(membership)
+}
+
+#[tauri::command]
+pub fn pty_leave_group(
+    app: AppHandle,
     state: State<'_, PtyManager>,
     session_id: String,
-    data: String,
+    group: String,
+) -> Result<GroupMembership, String> {
+    let membership = state.leave_group(&session_id, &group)?;
+    let _ = app.emit(GROUP_CHANGED_EVENT, &membership);
+    Ok(membership)
+}
+
+/// Acknowledge that the frontend has rendered `bytes` bytes of output.
+#[tauri::command]
+pub fn pty_ack(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    bytes: usize,
 ) -> Result<(), String> {
-    let bytes = base64::engine::general_purpose::STANDARD
-        .decode(&data)
-        .map_err(|e| format!("Invalid base64: {e}"))?;
-    state.write_to_session(&session_id, &bytes)
+    state.ack_session_output(&session_id, bytes)
 }
 
 #[tauri::command]
@@ -37,9 +152,26 @@ pub fn pty_resize(
     state.resize_session(&session_id, cols, rows)
 }
 
+/// Stop a session with SIGHUP, escalating to SIGKILL after `grace_ms`.
+/// Runs off the main thread since it may block for the whole grace period.
 #[tauri::command]
-pub fn pty_destroy(state: State<'_, PtyManager>, session_id: String) {
-    state.destroy_session(&session_id);
+pub async fn pty_destroy(
+    app: AppHandle,
+    session_id: String,
+    grace_ms: Option<u64>,
+) -> Result<PtyTeardown, String> {
+    let grace = Duration::from_millis(grace_ms.unwrap_or(DEFAULT_GRACE_MS));
+    tauri::async_runtime::spawn_blocking(move || {
+        let manager = app.state::<PtyManager>();
+        for membership in manager.leave_all_groups(&session_id) {
+            let _ = app.emit(GROUP_CHANGED_EVENT, &membership);
+        }
+        manager
+            .destroy_session(&session_id, grace)
+            .ok_or_else(|| format!("Session {session_id} not found"))
+    })
+    .await
+    .map_err(|e| format!("Teardown task failed: {e}"))?
 }
 
 #[tauri::command]
@@ -51,3 +183,174 @@ pub fn pty_get_cwd(
         .get_session_cwd(&session_id)
         .ok_or_else(|| format!("Session {session_id} not found"))
 }
+
+#[tauri::command]
+pub fn pty_get_foreground_process(
+    state: State<'_, PtyManager>,
+    session_id: String,
+) -> Result<Option<ForegroundProcess>, String> {
+    state.get_session_foreground_process(&session_id)
+}
+
+/// Commands run in the session so far, from the shell's OSC 133 marks.
+#[tauri::command]
+pub fn pty_list_blocks(
+    state: State<'_, PtyManager>,
+    session_id: String,
+) -> Result<Vec<CommandBlock>, String> {
+    state.list_session_blocks(&session_id)
+}
+
+#[tauri::command]
+pub fn pty_get_block_output(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    block_id: u64,
+) -> Result<BlockOutput, String> {
+    state.get_session_block_output(&session_id, block_id)
+}
+
+/// Start recording the session to an asciicast v2 file. Without a `path`,
+/// the file goes under `~/.terminal-plus/recordings/`.
+#[tauri::command]
+pub fn pty_start_recording(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    path: Option<String>,
+) -> Result<RecordingInfo, String> {
+    let path = path.unwrap_or_else(|| recording::default_path(&session_id));
+    state.start_session_recording(&session_id, path)
+}
+
+#[tauri::command]
+pub fn pty_stop_recording(
+    state: State<'_, PtyManager>,
+    session_id: String,
+) -> Result<RecordingInfo, String> {
+    state.stop_session_recording(&session_id)
+}
+
+/// Play an asciicast recording back in a read-only session. Output arrives
+/// on `on_output` exactly like a live session's.
+#[tauri::command]
+pub fn pty_replay_open(
+    app: AppHandle,
+    state: State<'_, PtyManager>,
+    session_id: String,
+    path: String,
+    on_output: Channel,
+) -> Result<ReplayState, String> {
+    state.open_replay(app, session_id, &path, on_output)
+}
+
+#[tauri::command]
+pub fn pty_replay_pause(
+    state: State<'_, PtyManager>,
+    session_id: String,
+) -> Result<ReplayState, String> {
+    state.control_session_replay(&session_id, ReplayControl::Pause)
+}
+
+#[tauri::command]
+pub fn pty_replay_resume(
+    state: State<'_, PtyManager>,
+    session_id: String,
+) -> Result<ReplayState, String> {
+    state.control_session_replay(&session_id, ReplayControl::Resume)
+}
+
+#[tauri::command]
+pub fn pty_replay_seek(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    position_ms: u64,
+) -> Result<ReplayState, String> {
+    let position = Duration::from_millis(position_ms);
+    state.control_session_replay(&session_id, ReplayControl::Seek(position))
+}
+
+#[tauri::command]
+pub fn pty_replay_set_speed(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    speed: f64,
+) -> Result<ReplayState, String> {
+    state.control_session_replay(&session_id, ReplayControl::Speed(speed))
+}
+
+/// What the session's screen shows, from the backend's own terminal model.
+/// Without a `range`, returns the visible screen; `styled` keeps colors and
+/// attributes as SGR sequences.
+#[tauri::command]
+pub fn pty_get_screen_text(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    range: Option<LineRange>,
+    styled: Option<bool>,
+) -> Result<ScreenText, String> {
+    state.get_session_screen_text(&session_id, range, styled.unwrap_or(false))
+}
+
+/// Search everything the session has printed, including output long gone from
+/// the screen model's scrollback. Returns the newest `limit` matching lines.
+#[tauri::command]
+pub async fn pty_search(
+    app: AppHandle,
+    session_id: String,
+    pattern: String,
+    case_sensitive: Option<bool>,
+    limit: Option<usize>,
+) -> Result<SearchResults, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<PtyManager>().search_session(
+            &session_id,
+            &pattern,
+            case_sensitive.unwrap_or(false),
+            limit.unwrap_or(DEFAULT_SEARCH_LIMIT),
+        )
+    })
+    .await
+    .map_err(|e| format!("Search task failed: {e}"))?
+}
+
+/// Mute or unmute a session's bell and OSC 9/777 notifications.
+#[tauri::command]
+pub fn pty_set_notifications_muted(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    muted: bool,
+) -> Result<(), String> {
+    state.set_session_notifications_muted(&session_id, muted)
+}
+
+/// Monitor a session like tmux's `monitor-activity` / `monitor-silence`:
+/// `pty-monitor-{id}` fires when output follows `activityAfterMs` of quiet, or
+/// after `silenceAfterMs` without output. Omitting a threshold turns that monitor
+/// off; thresholds under a second are rejected.
+#[tauri::command]
+pub fn pty_set_monitor(
+    state: State<'_, PtyManager>,
+    session_id: String,
+    activity_after_ms: Option<u64>,
+    silence_after_ms: Option<u64>,
+) -> Result<(), String> {
+    let threshold = |ms: Option<u64>| match ms.map(Duration::from_millis) {
+        Some(after) if after < monitor::MIN_THRESHOLD => Err(format!(
+            "Monitor thresholds must be at least {} ms",
+            monitor::MIN_THRESHOLD.as_millis()
+        )),
+        after => Ok(after),
+    };
+    state.set_session_monitor(
+        &session_id,
+        threshold(activity_after_ms)?,
+        threshold(silence_after_ms)?,
+    )
+}
+
+/// Regenerate the shell integration's prompt and highlighting colors from the
+/// app theme. Running shells re-source them before their next prompt.
+#[tauri::command]
+pub fn pty_set_prompt_palette(palette: PromptPalette) -> Result<(), String> {
+    palette::apply_palette(&palette)
+}
@@ -0,0 +1,104 @@
+use std::collections::VecDeque;
+
+/// How much raw output each session keeps for later lookups.
+pub const HISTORY_CAPACITY: usize = 4 * 1024 * 1024;
+
+/// The most recent output of a session, addressed by stream offset
+/// (bytes since the session started).
+pub struct OutputHistory {
+    buf: VecDeque<u8>,
+    /// Stream offset of `buf[0]`.
+    start: u64,
+    capacity: usize,
+}
+
+impl OutputHistory {
+    pub fn new(capacity: usize) -> Self {
+        OutputHistory {
+            buf: VecDeque::new(),
+            start: 0,
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        if data.len() >= self.capacity {
+            let skip = data.len() - self.capacity;
+            self.start += (self.buf.len() + skip) as u64;
+            self.buf.clear();
+            self.buf.extend(&data[skip..]);
+            return;
+        }
+        let overflow = (self.buf.len() + data.len()).saturating_sub(self.capacity);
+        self.buf.drain(..overflow);
+        self.start += overflow as u64;
+        self.buf.extend(data);
+    }
+
+    /// Stream offset just past the newest byte.
+    pub fn end(&self) -> u64 {
+        self.start + self.buf.len() as u64
+    }
+
+    /// The bytes in `from..to` that are still retained, and whether the
+    /// beginning of the range has already been discarded.
+    pub fn range(&self, from: u64, to: u64) -> (Vec<u8>, bool) {
+        let truncated = from < self.start;
+        let from = from.clamp(self.start, self.end());
+        let to = to.clamp(from, self.end());
+        let bytes = self
+            .buf
+            .range((from - self.start) as usize..(to - self.start) as usize)
+            .copied()
+            .collect();
+        (bytes, truncated)
+    }
+
+    /// The last `max` bytes for a reader starting mid-stream: from just past
+    /// the first newline, rather than halfway through an escape sequence,
+    /// unless they go back to the start of the stream.
+    pub fn tail_from_line(&self, max: usize) -> Vec<u8> {
+        let end = self.end();
+        let from = end.saturating_sub(max as u64);
+        let (mut tail, truncated) = self.range(from, end);
+        if truncated || from > 0 {
+            let cut = tail.iter().position(|&b| b == b'\n').map_or(0, |i| i + 1);
+            tail.drain(..cut);
+        }
+        tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_of_the_whole_stream_is_kept_as_is() {
+        let mut history = OutputHistory::new(64);
+        history.push(b"\x1b[1mone\r\ntwo");
+        assert_eq!(history.tail_from_line(64), b"\x1b[1mone\r\ntwo");
+    }
+
+    #[test]
+    fn tail_starts_after_the_first_newline() {
+        let mut history = OutputHistory::new(64);
+        history.push(b"one\r\ntwo\r\nthree");
+        assert_eq!(history.tail_from_line(10), b"three");
+        assert_eq!(history.tail_from_line(11), b"two\r\nthree");
+    }
+
+    #[test]
+    fn tail_past_what_was_discarded_starts_after_the_first_newline() {
+        let mut history = OutputHistory::new(8);
+        history.push(b"one\r\ntwo\r\nthree");
+        assert_eq!(history.tail_from_line(64), b"three");
+    }
+
+    #[test]
+    fn tail_without_a_newline_is_kept() {
+        let mut history = OutputHistory::new(64);
+        history.push(b"abcdef");
+        assert_eq!(history.tail_from_line(3), b"def");
+    }
+}
@@ -1,33 +1,221 @@
-use parking_lot::RwLock;
-use std::collections::HashMap;
-use tauri::AppHandle;
+use parking_lot::{Mutex, RwLock};
+use regex::RegexBuilder;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+#[cfg(unix)]
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter};
 
-use super::session::PtySession;
+use super::backend::SessionBackend;
+use super::output;
+use super::replay::{ReplayControl, ReplaySession};
+use super::session::{CarriedSettings, PtySession, SpawnOptions};
+use super::types::{
+    BlockOutput, CommandBlock, ForegroundProcess, GroupMembership, LineRange, PtyExit, PtyTeardown,
+    RecordingInfo, ReplayState, ScreenText, SearchResults, SessionInfo, GROUP_CHANGED_EVENT,
+};
+#[cfg(unix)]
+use crate::daemon::{client::DaemonClient, DaemonConfig};
 
 pub struct PtyManager {
-    sessions: RwLock<HashMap<String, PtySession>>,
+    sessions: RwLock<HashMap<String, Arc<dyn SessionBackend>>>,
+    /// Broadcast groups: input written to a group goes to every member.
+    groups: RwLock<HashMap<String, BTreeSet<String>>>,
+    /// Set when sessions run in the session daemon instead of this process.
+    #[cfg(unix)]
+    daemon: OnceLock<Arc<DaemonClient>>,
+    /// Why the session daemon couldn't be used, or sessions it kept couldn't
+    /// be taken over; for the frontend to show.
+    daemon_errors: Mutex<Vec<String>>,
+}
+
+impl Default for PtyManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PtyManager {
     pub fn new() -> Self {
         PtyManager {
             sessions: RwLock::new(HashMap::new()),
+            groups: RwLock::new(HashMap::new()),
+            #[cfg(unix)]
+            daemon: OnceLock::new(),
+            daemon_errors: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Run sessions in the session daemon if settings.json turns it on, and
+    /// take over the ones it kept running from the last run of the app.
+    /// Failures are kept for [`PtyManager::daemon_errors`]; sessions then run
+    /// in-process.
+    #[cfg(unix)]
+    pub fn connect_daemon(&self, app: &AppHandle) {
+        if let Err(e) = self.try_connect_daemon(app) {
+            self.daemon_errors.lock().push(format!(
+                "Session daemon unavailable, running sessions in-process: {e}"
+            ));
+        }
+    }
+
+    #[cfg(unix)]
+    fn try_connect_daemon(&self, app: &AppHandle) -> Result<(), String> {
+        if !DaemonConfig::load()?.enabled {
+            return Ok(());
         }
+        let client = Arc::new(DaemonClient::connect_or_start()?);
+        let entries = client.list()?;
+        let _ = self.daemon.set(client.clone());
+        for entry in entries {
+            let session_id = entry.session_id.clone();
+            match PtySession::adopt(app.clone(), client.clone(), entry, output::unattached()) {
+                Ok(session) => {
+                    self.sessions.write().insert(session_id, Arc::new(session));
+                }
+                Err(e) => self
+                    .daemon_errors
+                    .lock()
+                    .push(format!("Failed to take over session {session_id}: {e}")),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    pub fn daemon(&self) -> Option<Arc<DaemonClient>> {
+        self.daemon.get().cloned()
+    }
+
+    pub fn daemon_errors(&self) -> Vec<String> {
+        self.daemon_errors.lock().clone()
     }
 
     pub fn create_session(
         &self,
         app: AppHandle,
         session_id: String,
-        cols: u16,
-        rows: u16,
-        cwd: Option<String>,
+        options: SpawnOptions,
+        on_output: Channel,
     ) -> Result<(), String> {
-        let session = PtySession::spawn(app, session_id.clone(), cols, rows, cwd)?;
-        self.sessions.write().insert(session_id, session);
+        self.check_free(&session_id)?;
+        let session = PtySession::spawn(app, session_id.clone(), options, on_output)?;
+        self.insert_new(session_id, Arc::new(session))
+    }
+
+    /// Start a new run of a session whose program exited under
+    /// [`ExitPolicy::Restart`](super::session::ExitPolicy::Restart), in place of
+    /// the exited one. Does nothing if the session was destroyed meanwhile. If
+    /// the program can't be started again, the session is removed and the
+    /// error goes out as another `pty-exit-{id}`.
+    pub fn respawn_session(
+        &self,
+        app: AppHandle,
+        session_id: String,
+        options: SpawnOptions,
+        on_output: Channel,
+        carried: CarriedSettings,
+    ) {
+        if !self.sessions.read().contains_key(&session_id) {
+            return;
+        }
+        let session = match PtySession::spawn(app.clone(), session_id.clone(), options, on_output) {
+            Ok(session) => session,
+            Err(e) => {
+                if self.sessions.write().remove(&session_id).is_some() {
+                    for membership in self.leave_all_groups(&session_id) {
+                        let _ = app.emit(GROUP_CHANGED_EVENT, &membership);
+                    }
+                    let exit = PtyExit {
+                        exit_code: None,
+                        signal: None,
+                        signal_name: None,
+                        runtime_ms: 0,
+                        error: Some(e),
+                    };
+                    let _ = app.emit(&format!("pty-exit-{session_id}"), exit);
+                }
+                return;
+            }
+        };
+        let _ = session.set_notifications_muted(carried.notifications_muted);
+        let (activity_after, silence_after) = carried.monitor;
+        let _ = session.set_monitor(activity_after, silence_after);
+        let mut sessions = self.sessions.write();
+        match sessions.get_mut(&session_id) {
+            Some(slot) => *slot = Arc::new(session),
+            None => {
+                drop(sessions);
+                session.shutdown(Duration::ZERO);
+            }
+        }
+    }
+
+    /// Open a `.cast` recording as a read-only session that starts playing immediately.
+    pub fn open_replay(
+        &self,
+        app: AppHandle,
+        session_id: String,
+        path: &str,
+        on_output: Channel,
+    ) -> Result<ReplayState, String> {
+        self.check_free(&session_id)?;
+        let (session, state) = ReplaySession::open(app, session_id.clone(), path, on_output)?;
+        self.insert_new(session_id, Arc::new(session))?;
+        Ok(state)
+    }
+
+    fn check_free(&self, session_id: &str) -> Result<(), String> {
+        if self.sessions.read().contains_key(session_id) {
+            return Err(format!("Session {session_id} already exists"));
+        }
         Ok(())
     }
 
+    /// Add a session just started, unless another one took its id meanwhile;
+    /// then it's shut down again rather than left running unreachable.
+    fn insert_new(
+        &self,
+        session_id: String,
+        session: Arc<dyn SessionBackend>,
+    ) -> Result<(), String> {
+        let mut sessions = self.sessions.write();
+        if sessions.contains_key(&session_id) {
+            drop(sessions);
+            session.shutdown(Duration::ZERO);
+            return Err(format!("Session {session_id} already exists"));
+        }
+        sessions.insert(session_id, session);
+        Ok(())
+    }
+
+    /// Register a session started elsewhere, e.g. a tmux pane.
+    pub fn insert_session(&self, session_id: String, session: Arc<dyn SessionBackend>) {
+        self.sessions.write().insert(session_id, session);
+    }
+
+    /// Sessions a frontend can reattach to, oldest first.
+    pub fn list_sessions(&self) -> Vec<SessionInfo> {
+        let mut infos: Vec<_> = self
+            .sessions
+            .read()
+            .values()
+            .filter_map(|session| session.info())
+            .collect();
+        infos.sort_by_key(|info| info.started_at);
+        infos
+    }
+
+    pub fn attach_session(&self, session_id: &str, on_output: Channel) -> Result<(), String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.attach(on_output)
+    }
+
     pub fn write_to_session(&self, session_id: &str, data: &[u8]) -> Result<(), String> {
         let sessions = self.sessions.read();
         let session = sessions
@@ -36,6 +224,114 @@ impl PtyManager {
         session.write(data)
     }
 
+    /// Wait up to `timeout` for a session's shell to draw a prompt. The
+    /// sessions stay unlocked meanwhile.
+    pub fn wait_for_prompt(&self, session_id: &str, timeout: Duration) -> Result<bool, String> {
+        let session = self
+            .sessions
+            .read()
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        Ok(session.wait_for_prompt(timeout))
+    }
+
+    /// Write the same input to every member of `group`. Every member is tried;
+    /// the error lists the ones that failed.
+    pub fn write_to_group(&self, group: &str, data: &[u8]) -> Result<(), String> {
+        let members = self
+            .groups
+            .read()
+            .get(group)
+            .cloned()
+            .ok_or_else(|| format!("Group {group} not found"))?;
+        // Written unlocked: one member that isn't reading its input mustn't
+        // hold up the others, or every session being created or destroyed
+        let targets: Vec<_> = {
+            let sessions = self.sessions.read();
+            members
+                .iter()
+                .map(|id| (id, sessions.get(id).cloned()))
+                .collect()
+        };
+        let failures: Vec<String> = targets
+            .into_iter()
+            .filter_map(|(id, session)| {
+                let result = match session {
+                    Some(session) => session.write(data),
+                    None => Err("session not found".to_string()),
+                };
+                result.err().map(|e| format!("{id}: {e}"))
+            })
+            .collect();
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Broadcast to {group} failed for {}",
+                failures.join("; ")
+            ))
+        }
+    }
+
+    pub fn join_group(&self, session_id: &str, group: &str) -> Result<GroupMembership, String> {
+        if group.is_empty() {
+            return Err("Group name can't be empty".to_string());
+        }
+        if !self.sessions.read().contains_key(session_id) {
+            return Err(format!("Session {session_id} not found"));
+        }
+        let mut groups = self.groups.write();
+        let members = groups.entry(group.to_string()).or_default();
+        members.insert(session_id.to_string());
+        Ok(GroupMembership {
+            group: group.to_string(),
+            members: members.iter().cloned().collect(),
+        })
+    }
+
+    pub fn leave_group(&self, session_id: &str, group: &str) -> Result<GroupMembership, String> {
+        let mut groups = self.groups.write();
+        let members = groups
+            .get_mut(group)
+            .filter(|members| members.contains(session_id))
+            .ok_or_else(|| format!("Session {session_id} is not in group {group}"))?;
+        members.remove(session_id);
+        let membership = GroupMembership {
+            group: group.to_string(),
+            members: members.iter().cloned().collect(),
+        };
+        if members.is_empty() {
+            groups.remove(group);
+        }
+        Ok(membership)
+    }
+
+    /// Take a session out of every group it is in, e.g. when it is destroyed.
+    pub fn leave_all_groups(&self, session_id: &str) -> Vec<GroupMembership> {
+        let mut groups = self.groups.write();
+        let mut changed = Vec::new();
+        groups.retain(|group, members| {
+            if members.remove(session_id) {
+                changed.push(GroupMembership {
+                    group: group.clone(),
+                    members: members.iter().cloned().collect(),
+                });
+            }
+            !members.is_empty()
+        });
+        changed
+    }
+
+    pub fn ack_session_output(&self, session_id: &str, bytes: usize) -> Result<(), String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.ack(bytes);
+        Ok(())
+    }
+
     pub fn resize_session(&self, session_id: &str, cols: u16, rows: u16) -> Result<(), String> {
         let sessions = self.sessions.read();
         let session = sessions
@@ -44,14 +340,143 @@ impl PtyManager {
         session.resize(cols, rows)
     }
 
-    pub fn destroy_session(&self, session_id: &str) {
-        self.sessions.write().remove(session_id);
+    /// Remove the session and shut it down, blocking for up to `grace` plus
+    /// the SIGKILL timeout. Returns `None` if the session doesn't exist.
+    pub fn destroy_session(&self, session_id: &str, grace: Duration) -> Option<PtyTeardown> {
+        // Take it out of the map first so the lock isn't held during the grace period
+        let session = self.sessions.write().remove(session_id)?;
+        Some(session.shutdown(grace))
     }
 
     pub fn get_session_cwd(&self, session_id: &str) -> Option<String> {
-        self.sessions
+        let session = self.sessions.read().get(session_id).cloned();
+        session.map(|s| s.get_cwd())
+    }
+
+    pub fn get_session_foreground_process(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<ForegroundProcess>, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        Ok(session.foreground_process())
+    }
+
+    pub fn list_session_blocks(&self, session_id: &str) -> Result<Vec<CommandBlock>, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        Ok(session.blocks())
+    }
+
+    pub fn get_session_block_output(
+        &self,
+        session_id: &str,
+        block_id: u64,
+    ) -> Result<BlockOutput, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session
+            .block_output(block_id)
+            .ok_or_else(|| format!("Block {block_id} not found"))
+    }
+
+    pub fn start_session_recording(
+        &self,
+        session_id: &str,
+        path: String,
+    ) -> Result<RecordingInfo, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.start_recording(path)
+    }
+
+    pub fn stop_session_recording(&self, session_id: &str) -> Result<RecordingInfo, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session
+            .stop_recording()
+            .ok_or_else(|| format!("Session {session_id} is not being recorded"))
+    }
+
+    pub fn control_session_replay(
+        &self,
+        session_id: &str,
+        control: ReplayControl,
+    ) -> Result<ReplayState, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.control_replay(control)
+    }
+
+    pub fn get_session_screen_text(
+        &self,
+        session_id: &str,
+        range: Option<LineRange>,
+        styled: bool,
+    ) -> Result<ScreenText, String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.screen_text(range, styled)
+    }
+
+    /// Search a session's full history for `pattern`, a regular expression.
+    /// The sessions lock is released before searching, which can take a while.
+    pub fn search_session(
+        &self,
+        session_id: &str,
+        pattern: &str,
+        case_sensitive: bool,
+        limit: usize,
+    ) -> Result<SearchResults, String> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid search pattern: {e}"))?;
+        let session = self
+            .sessions
             .read()
             .get(session_id)
-            .map(|s| s.get_cwd())
+            .cloned()
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.search(&regex, limit)
+    }
+
+    pub fn set_session_notifications_muted(
+        &self,
+        session_id: &str,
+        muted: bool,
+    ) -> Result<(), String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.set_notifications_muted(muted)
+    }
+
+    pub fn set_session_monitor(
+        &self,
+        session_id: &str,
+        activity_after: Option<Duration>,
+        silence_after: Option<Duration>,
+    ) -> Result<(), String> {
+        let sessions = self.sessions.read();
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| format!("Session {session_id} not found"))?;
+        session.set_monitor(activity_after, silence_after)
     }
 }
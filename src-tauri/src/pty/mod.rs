@@ -1,3 +1,18 @@
+pub mod backend;
+pub mod blocks;
 pub mod commands;
+pub mod history;
 pub mod manager;
+pub mod monitor;
+pub mod notify;
+pub mod osc;
+pub mod output;
+pub mod palette;
+pub mod process;
+pub mod profile;
+pub mod recording;
+pub mod replay;
+pub mod screen;
+pub mod scrollback;
 pub mod session;
+pub mod types;
@@ -0,0 +1,136 @@
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use super::types::{MonitorAlert, MonitorKind};
+
+/// Shortest threshold either monitor accepts; below it every read would be
+/// "activity".
+pub const MIN_THRESHOLD: Duration = Duration::from_secs(1);
+
+struct MonitorState {
+    /// Report output that follows at least this much quiet.
+    activity_after: Option<Duration>,
+    /// Report when there has been no output for this long.
+    silence_after: Option<Duration>,
+    last_output: Instant,
+    /// Silence was reported for the current quiet period.
+    silence_reported: bool,
+    /// Flow control has stopped reading: the program has output waiting, so
+    /// it isn't silent.
+    paused: bool,
+    closed: bool,
+}
+
+/// tmux-style `monitor-activity` / `monitor-silence` for one session. The
+/// reader thread reports output; a watcher thread times the silences, so
+/// both work whether or not the pane is visible.
+pub struct OutputMonitor {
+    state: Mutex<MonitorState>,
+    cond: Condvar,
+    app: AppHandle,
+    event: String,
+}
+
+impl OutputMonitor {
+    /// Create the monitor, off until [`OutputMonitor::configure`], and start its watcher thread.
+    pub fn spawn(app: AppHandle, session_id: &str) -> Arc<Self> {
+        let monitor = Arc::new(OutputMonitor {
+            state: Mutex::new(MonitorState {
+                activity_after: None,
+                silence_after: None,
+                last_output: Instant::now(),
+                silence_reported: false,
+                paused: false,
+                closed: false,
+            }),
+            cond: Condvar::new(),
+            app,
+            event: format!("pty-monitor-{session_id}"),
+        });
+        let watcher = monitor.clone();
+        std::thread::spawn(move || watcher.watch_silence());
+        monitor
+    }
+
+    /// Turn either monitor on (with its threshold) or off (`None`).
+    pub fn configure(&self, activity_after: Option<Duration>, silence_after: Option<Duration>) {
+        let mut state = self.state.lock();
+        state.activity_after = activity_after;
+        state.silence_after = silence_after;
+        state.silence_reported = false;
+        self.cond.notify_all();
+    }
+
+    /// The current thresholds, as given to [`OutputMonitor::configure`].
+    pub fn thresholds(&self) -> (Option<Duration>, Option<Duration>) {
+        let state = self.state.lock();
+        (state.activity_after, state.silence_after)
+    }
+
+    /// The session produced output.
+    pub fn output(&self) {
+        let mut state = self.state.lock();
+        let quiet = state.last_output.elapsed();
+        state.last_output = Instant::now();
+        if state.silence_reported {
+            // Re-arm the watcher, which is waiting for output
+            state.silence_reported = false;
+            self.cond.notify_all();
+        }
+        let activity = state.activity_after.is_some_and(|after| quiet >= after);
+        drop(state);
+        if activity {
+            self.alert(MonitorKind::Activity, quiet);
+        }
+    }
+
+    /// The reader stopped or resumed reading under flow control. Time spent
+    /// paused doesn't count as quiet.
+    pub fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock();
+        state.paused = paused;
+        if !paused {
+            state.last_output = Instant::now();
+        }
+        self.cond.notify_all();
+    }
+
+    /// Stop the watcher thread; the session has ended.
+    pub fn close(&self) {
+        self.state.lock().closed = true;
+        self.cond.notify_all();
+    }
+
+    fn alert(&self, kind: MonitorKind, quiet: Duration) {
+        let alert = MonitorAlert {
+            kind,
+            quiet_ms: quiet.as_millis() as u64,
+        };
+        let _ = self.app.emit(&self.event, alert);
+    }
+
+    fn watch_silence(&self) {
+        let mut state = self.state.lock();
+        while !state.closed {
+            let Some(after) = state
+                .silence_after
+                .filter(|_| !state.silence_reported && !state.paused)
+            else {
+                self.cond.wait(&mut state);
+                continue;
+            };
+            let due = state.last_output + after;
+            if Instant::now() < due {
+                self.cond.wait_until(&mut state, due);
+                continue;
+            }
+            state.silence_reported = true;
+            let quiet = state.last_output.elapsed();
+            drop(state);
+            self.alert(MonitorKind::Silence, quiet);
+            state = self.state.lock();
+        }
+    }
+}
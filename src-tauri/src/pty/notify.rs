@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+use super::osc::Osc;
+use super::types::{NotificationKind, PtyNotification};
+
+/// At most this many notifications per session within `RATE_WINDOW`; the rest are dropped.
+const RATE_LIMIT: usize = 3;
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Title of native notifications that don't bring their own.
+const DEFAULT_TITLE: &str = "Terminal+";
+
+/// The notification an OSC 9 or OSC 777 sequence asks for, if any.
+pub fn from_osc(seq: &Osc) -> Option<PtyNotification> {
+    match seq.code {
+        9 => {
+            // `9;<number>;...` are ConEmu commands (e.g. `9;4` progress), not messages
+            let first = seq.data.split(';').next().unwrap_or_default();
+            if seq.data.is_empty() || first.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            Some(PtyNotification {
+                kind: NotificationKind::Osc9,
+                title: None,
+                body: Some(seq.data.clone()),
+            })
+        }
+        777 => {
+            let mut params = seq.data.splitn(3, ';');
+            if params.next() != Some("notify") {
+                return None;
+            }
+            Some(PtyNotification {
+                kind: NotificationKind::Osc777,
+                title: params.next().map(str::to_string),
+                body: params.next().map(str::to_string),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Keeps a noisy session (a bell per keystroke, a script in a loop) from
+/// flooding the user.
+#[derive(Default)]
+pub struct RateLimiter {
+    recent: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    pub fn allow(&mut self) -> bool {
+        let now = Instant::now();
+        while self
+            .recent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > RATE_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= RATE_LIMIT {
+            return false;
+        }
+        self.recent.push_back(now);
+        true
+    }
+}
+
+/// Raise a native notification, unless one of the app's windows has focus.
+/// `cwd` is the session's directory, used to say where a bell came from.
+pub fn show_native(app: &AppHandle, notification: &PtyNotification, cwd: Option<&str>) {
+    let focused = app
+        .webview_windows()
+        .values()
+        .any(|window| window.is_focused().unwrap_or(false));
+    if focused {
+        return;
+    }
+    let body = match (&notification.body, notification.kind, cwd) {
+        (Some(body), _, _) => body.clone(),
+        (None, NotificationKind::Bell, Some(cwd)) => format!("Bell in {cwd}"),
+        (None, NotificationKind::Bell, None) => "Bell".to_string(),
+        (None, _, _) => String::new(),
+    };
+    let _ = app
+        .notification()
+        .builder()
+        .title(notification.title.as_deref().unwrap_or(DEFAULT_TITLE))
+        .body(body)
+        .show();
+}
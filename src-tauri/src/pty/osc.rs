@@ -0,0 +1,179 @@
+/// Longest OSC payload we keep; anything longer is dropped rather than buffered.
+const MAX_OSC_LEN: usize = 4096;
+
+/// An Operating System Command sequence (`ESC ] <code> ; <data> BEL|ST`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Osc {
+    pub code: u32,
+    pub data: String,
+    /// Stream offset of the introducing ESC.
+    pub start: u64,
+    /// Stream offset just past the terminator.
+    pub end: u64,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    /// Saw ESC inside an OSC — either the start of ST or an aborted sequence.
+    OscEscape,
+}
+
+/// Picks OSC sequences out of the raw PTY stream.
+///
+/// Only looks, never filters: the bytes still go to the frontend untouched.
+/// Keeps its state between calls, so a sequence split across two reads is
+/// still recognised.
+#[derive(Default)]
+pub struct OscScanner {
+    state: State,
+    buf: Vec<u8>,
+    overflow: bool,
+    /// Total bytes fed so far.
+    offset: u64,
+    /// Offset of the ESC that started the current sequence.
+    seq_start: u64,
+}
+
+impl OscScanner {
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Osc> {
+        let mut found = Vec::new();
+        for &byte in data {
+            self.offset += 1;
+            match self.state {
+                State::Ground => {
+                    if byte == 0x1b {
+                        self.seq_start = self.offset - 1;
+                        self.state = State::Escape;
+                    }
+                }
+                State::Escape => {
+                    self.state = match byte {
+                        b']' => {
+                            self.buf.clear();
+                            self.overflow = false;
+                            State::Osc
+                        }
+                        0x1b => {
+                            self.seq_start = self.offset - 1;
+                            State::Escape
+                        }
+                        _ => State::Ground,
+                    };
+                }
+                State::Osc => match byte {
+                    0x07 => {
+                        found.extend(self.finish());
+                        self.state = State::Ground;
+                    }
+                    0x1b => self.state = State::OscEscape,
+                    // CAN / SUB abort the sequence
+                    0x18 | 0x1a => self.state = State::Ground,
+                    _ => {
+                        if self.buf.len() < MAX_OSC_LEN {
+                            self.buf.push(byte);
+                        } else {
+                            self.overflow = true;
+                        }
+                    }
+                },
+                State::OscEscape => {
+                    if byte == b'\\' {
+                        found.extend(self.finish());
+                        self.state = State::Ground;
+                    } else if byte == b']' {
+                        // Unterminated OSC followed directly by a new one
+                        self.seq_start = self.offset - 2;
+                        self.buf.clear();
+                        self.overflow = false;
+                        self.state = State::Osc;
+                    } else {
+                        self.state = State::Ground;
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    fn finish(&mut self) -> Option<Osc> {
+        if self.overflow {
+            return None;
+        }
+        let raw = String::from_utf8_lossy(&self.buf);
+        let (code, data) = raw.split_once(';').unwrap_or((&raw, ""));
+        Some(Osc {
+            code: code.parse().ok()?,
+            data: data.to_string(),
+            start: self.seq_start,
+            end: self.offset,
+        })
+    }
+}
+
+/// Parse an OSC 7 payload (`file://host/path`) into a local path.
+/// Returns `None` for malformed URLs and for directories on another host
+/// (e.g. a shell inside `ssh` that also emits OSC 7).
+pub fn parse_cwd_url(data: &str) -> Option<String> {
+    let rest = data.strip_prefix("file://")?;
+    let slash = rest.find('/')?;
+    let (host, path) = rest.split_at(slash);
+    if !is_local_host(host) {
+        return None;
+    }
+    Some(percent_decode(path))
+}
+
+fn is_local_host(host: &str) -> bool {
+    if host.is_empty() || host == "localhost" {
+        return true;
+    }
+    match local_hostname() {
+        Some(local) => {
+            // Compare short names too: shells report either form depending on config
+            let short = |h: &str| h.split('.').next().unwrap_or(h).to_ascii_lowercase();
+            host.eq_ignore_ascii_case(&local) || short(host) == short(&local)
+        }
+        // Can't tell — trust the shell
+        None => true,
+    }
+}
+
+#[cfg(unix)]
+fn local_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let ret = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if ret != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
+
+#[cfg(not(unix))]
+fn local_hostname() -> Option<String> {
+    None
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
@@ -0,0 +1,260 @@
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::ipc::{Channel, InvokeResponseBody};
+
+/// How long a small read is held back so following reads can join it.
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(4);
+
+/// Pending output is flushed immediately once it reaches this size,
+/// and no single message is larger than this.
+pub const MAX_BATCH: usize = 64 * 1024;
+
+/// The reader thread stops reading once this many bytes are unacknowledged...
+pub const HIGH_WATERMARK: usize = 512 * 1024;
+
+/// ...and resumes once the frontend has caught up to this many.
+pub const LOW_WATERMARK: usize = 128 * 1024;
+
+/// Where a session's output goes until a frontend attaches with `pty_attach`:
+/// nowhere, though it's still kept in the session's history.
+pub fn unattached() -> Channel {
+    Channel::new(|_| Ok(()))
+}
+
+struct Pending {
+    bytes: Vec<u8>,
+    /// When the oldest pending byte arrived.
+    since: Option<Instant>,
+    closed: bool,
+    /// Where output goes; replaced when the frontend reattaches.
+    channel: Channel,
+}
+
+struct Shared {
+    pending: Mutex<Pending>,
+    cond: Condvar,
+}
+
+#[derive(Default)]
+struct FlowState {
+    unacked: usize,
+    paused: bool,
+    closed: bool,
+}
+
+/// Ack-based flow control between the reader thread and the frontend.
+///
+/// Every byte pushed to the output stream counts as unacknowledged until the
+/// frontend reports it as rendered. While too much is outstanding the reader
+/// stops reading the PTY master, so the kernel buffer fills and the child
+/// blocks on write — the same backpressure a slow hardware terminal applies.
+#[derive(Default)]
+pub struct FlowControl {
+    state: Mutex<FlowState>,
+    cond: Condvar,
+}
+
+impl FlowControl {
+    fn sent(&self, bytes: usize) {
+        self.state.lock().unacked += bytes;
+    }
+
+    pub fn ack(&self, bytes: usize) {
+        let mut state = self.state.lock();
+        state.unacked = state.unacked.saturating_sub(bytes);
+        if state.paused && state.unacked <= LOW_WATERMARK {
+            state.paused = false;
+            self.cond.notify_all();
+        }
+    }
+
+    /// Whether the frontend is back down to the low watermark.
+    pub fn caught_up(&self) -> bool {
+        self.state.lock().unacked <= LOW_WATERMARK
+    }
+
+    /// Whether [`FlowControl::wait_for_capacity`] would block.
+    pub fn over_capacity(&self) -> bool {
+        let state = self.state.lock();
+        !state.closed && state.unacked >= HIGH_WATERMARK
+    }
+
+    /// Block the reader thread while the frontend is too far behind.
+    pub fn wait_for_capacity(&self) {
+        let mut state = self.state.lock();
+        if state.closed || state.unacked < HIGH_WATERMARK {
+            return;
+        }
+        state.paused = true;
+        while state.paused && !state.closed {
+            self.cond.wait(&mut state);
+        }
+    }
+
+    /// Start over with `unacked` bytes outstanding, e.g. for a new frontend
+    /// that will never acknowledge what the old one was sent.
+    fn reset(&self, unacked: usize) {
+        let mut state = self.state.lock();
+        state.unacked = unacked;
+        if state.paused && state.unacked <= LOW_WATERMARK {
+            state.paused = false;
+            self.cond.notify_all();
+        }
+    }
+
+    /// Stop applying backpressure for good (teardown), releasing a blocked reader.
+    pub fn close(&self) {
+        let mut state = self.state.lock();
+        state.closed = true;
+        state.paused = false;
+        self.cond.notify_all();
+    }
+}
+
+/// Streams PTY output to the frontend as raw bytes over a per-session channel.
+///
+/// The reader thread pushes every read; a flusher thread sends them on,
+/// merging reads that arrive within `COALESCE_WINDOW` into a single message.
+pub struct OutputStream {
+    shared: Arc<Shared>,
+    flow: Arc<FlowControl>,
+    flusher: Option<JoinHandle<()>>,
+}
+
+impl OutputStream {
+    pub fn new(channel: Channel, flow: Arc<FlowControl>) -> Self {
+        let shared = Arc::new(Shared {
+            pending: Mutex::new(Pending {
+                bytes: Vec::new(),
+                since: None,
+                closed: false,
+                channel,
+            }),
+            cond: Condvar::new(),
+        });
+        let flusher_shared = shared.clone();
+        let flusher = std::thread::spawn(move || flush_loop(&flusher_shared));
+        OutputStream {
+            shared,
+            flow,
+            flusher: Some(flusher),
+        }
+    }
+
+    /// Called by the reader thread before each read.
+    pub fn wait_for_capacity(&self) {
+        self.flow.wait_for_capacity();
+    }
+
+    pub fn over_capacity(&self) -> bool {
+        self.flow.over_capacity()
+    }
+
+    pub fn push(&self, data: &[u8]) {
+        self.flow.sent(data.len());
+        let mut pending = self.shared.pending.lock();
+        if pending.since.is_none() {
+            pending.since = Some(Instant::now());
+        }
+        pending.bytes.extend_from_slice(data);
+        self.shared.cond.notify_one();
+    }
+
+    /// The channel output currently goes to.
+    pub fn channel(&self) -> Channel {
+        self.shared.pending.lock().channel.clone()
+    }
+
+    /// A handle for redirecting the stream while the reader thread owns it.
+    pub fn switch(&self) -> OutputSwitch {
+        OutputSwitch {
+            shared: self.shared.clone(),
+            flow: self.flow.clone(),
+        }
+    }
+}
+
+/// Points an [`OutputStream`] at a new channel, e.g. after a webview reload.
+pub struct OutputSwitch {
+    shared: Arc<Shared>,
+    flow: Arc<FlowControl>,
+}
+
+impl OutputSwitch {
+    /// Send everything from now on to `channel`, starting with `backlog`.
+    ///
+    /// Output not flushed yet is discarded, so the caller must hold off the
+    /// reader and include it in `backlog`.
+    pub fn attach(&self, channel: Channel, backlog: Vec<u8>) {
+        self.flow.reset(backlog.len());
+        let mut pending = self.shared.pending.lock();
+        pending.channel = channel;
+        if pending.closed {
+            // The session has exited and the flusher is gone
+            for chunk in backlog.chunks(MAX_BATCH) {
+                let _ = pending
+                    .channel
+                    .send(InvokeResponseBody::Raw(chunk.to_vec()));
+            }
+            return;
+        }
+        pending.bytes = backlog;
+        pending.since = Some(Instant::now());
+        self.shared.cond.notify_one();
+    }
+}
+
+impl Drop for OutputStream {
+    /// Flush anything still pending and wait for the flusher thread to finish,
+    /// so nothing is sent after whatever the owner emits next (e.g. the exit event).
+    fn drop(&mut self) {
+        self.shared.pending.lock().closed = true;
+        self.shared.cond.notify_one();
+        if let Some(flusher) = self.flusher.take() {
+            let _ = flusher.join();
+        }
+    }
+}
+
+fn flush_loop(shared: &Shared) {
+    loop {
+        let (batch, closed, channel) = {
+            let mut pending = shared.pending.lock();
+            while !pending.closed && pending.bytes.len() < MAX_BATCH {
+                match pending.since {
+                    None => shared.cond.wait(&mut pending),
+                    Some(since) => {
+                        let deadline = since + COALESCE_WINDOW;
+                        if Instant::now() >= deadline {
+                            break;
+                        }
+                        shared.cond.wait_until(&mut pending, deadline);
+                    }
+                }
+            }
+            pending.since = None;
+            // Taken together with the batch: a batch from before a reattach
+            // must not reach the new channel, which gets it in its backlog
+            (
+                std::mem::take(&mut pending.bytes),
+                pending.closed,
+                pending.channel.clone(),
+            )
+        };
+        if batch.len() <= MAX_BATCH {
+            if !batch.is_empty() {
+                let _ = channel.send(InvokeResponseBody::Raw(batch));
+            }
+        } else {
+            // The reader got ahead of us; keep individual messages bounded
+            for chunk in batch.chunks(MAX_BATCH) {
+                let _ = channel.send(InvokeResponseBody::Raw(chunk.to_vec()));
+            }
+        }
+        if closed {
+            break;
+        }
+    }
+}
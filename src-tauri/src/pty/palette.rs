@@ -0,0 +1,317 @@
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Colors of the shell integration's prompt and syntax highlighting, taken
+/// from the app theme. Each is a `#rgb`, `#rrggbb` or `#rrggbbaa` hex color;
+/// alpha is ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptPalette {
+    pub user: String,
+    pub path: String,
+    pub branch: String,
+    /// The `❯` before the cursor.
+    pub prompt: String,
+    /// Clock, comments and autosuggestions.
+    pub muted: String,
+    pub command: String,
+    pub keyword: String,
+    pub string: String,
+    /// Options, globs and redirections.
+    pub option: String,
+    /// Plain arguments.
+    pub text: String,
+    pub error: String,
+}
+
+impl Default for PromptPalette {
+    /// Midnight Indigo, the default app theme.
+    fn default() -> Self {
+        PromptPalette {
+            user: "#C8F5DC".to_string(),
+            path: "#8B7DFF".to_string(),
+            branch: "#B040D4".to_string(),
+            prompt: "#3318E8".to_string(),
+            muted: "#3A3560".to_string(),
+            command: "#7EECF0".to_string(),
+            keyword: "#B040D4".to_string(),
+            string: "#C8F5DC".to_string(),
+            option: "#FFD580".to_string(),
+            text: "#D5F5E3".to_string(),
+            error: "#FF6B8A".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Rgb(u8, u8, u8);
+
+impl Rgb {
+    fn parse(name: &str, color: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid {name} color: {color}");
+        let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).unwrap_or(0);
+            if len == 1 {
+                value * 17
+            } else {
+                value
+            }
+        };
+        match hex.len() {
+            3 => Ok(Rgb(channel(0, 1), channel(1, 1), channel(2, 1))),
+            6 | 8 => Ok(Rgb(channel(0, 2), channel(1, 2), channel(2, 2))),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// `RRGGBB`, as fish's `set_color` takes it.
+    fn bare(self) -> String {
+        format!("{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+
+    /// `#RRGGBB`, for zsh and nushell.
+    fn hex(self) -> String {
+        format!("#{}", self.bare())
+    }
+
+    /// `R;G;B`, for a `38;2;R;G;B` SGR sequence in bash.
+    fn sgr(self) -> String {
+        format!("{};{};{}", self.0, self.1, self.2)
+    }
+}
+
+/// A validated [`PromptPalette`].
+struct Colors {
+    user: Rgb,
+    path: Rgb,
+    branch: Rgb,
+    prompt: Rgb,
+    muted: Rgb,
+    command: Rgb,
+    keyword: Rgb,
+    string: Rgb,
+    option: Rgb,
+    text: Rgb,
+    error: Rgb,
+}
+
+impl Colors {
+    fn new(palette: &PromptPalette) -> Result<Self, String> {
+        Ok(Colors {
+            user: Rgb::parse("user", &palette.user)?,
+            path: Rgb::parse("path", &palette.path)?,
+            branch: Rgb::parse("branch", &palette.branch)?,
+            prompt: Rgb::parse("prompt", &palette.prompt)?,
+            muted: Rgb::parse("muted", &palette.muted)?,
+            command: Rgb::parse("command", &palette.command)?,
+            keyword: Rgb::parse("keyword", &palette.keyword)?,
+            string: Rgb::parse("string", &palette.string)?,
+            option: Rgb::parse("option", &palette.option)?,
+            text: Rgb::parse("text", &palette.text)?,
+            error: Rgb::parse("error", &palette.error)?,
+        })
+    }
+}
+
+/// A shell with integration scripts, and the theme file next to them.
+#[derive(Clone, Copy)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+    Nu,
+}
+
+impl Shell {
+    const ALL: [Shell; 4] = [Shell::Zsh, Shell::Bash, Shell::Fish, Shell::Nu];
+
+    fn theme_path(self, home: &str) -> String {
+        let file = match self {
+            Shell::Zsh => "zsh/theme.zsh",
+            Shell::Bash => "bash/theme.bash",
+            Shell::Fish => "fish/theme.fish",
+            Shell::Nu => "nu/theme.nuon",
+        };
+        format!("{home}/.terminal-plus/shell/{file}")
+    }
+
+    /// The theme file. Its first line holds `version`; the integration scripts
+    /// compare it before each prompt and re-source the file when it changed.
+    fn theme(self, c: &Colors, version: u128) -> String {
+        match self {
+            Shell::Zsh => format!(
+                concat!(
+                    "_terminal_plus_theme_version={version}\n",
+                    "# Generated by Terminal+ from the app theme\n",
+                    "zstyle ':vcs_info:git:*' formats ' %F{{{branch}}}(%b)%f'\n",
+                    "PROMPT=$'\\n''%F{{{user}}}%n%f %F{{{path}}}%~%f${{vcs_info_msg_0_}}'$'\\n''%F{{{prompt}}}❯%f '\n",
+                    "RPROMPT='%F{{{muted}}}%T%f'\n",
+                    "if (( ${{+ZSH_HIGHLIGHT_STYLES}} )); then\n",
+                    "  ZSH_HIGHLIGHT_STYLES[command]='fg={command},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[builtin]='fg={command},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[alias]='fg={command},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[function]='fg={command},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[precommand]='fg={command},bold,underline'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[path]='fg={path},underline'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[path_prefix]='fg={path}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[globbing]='fg={option}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[single-quoted-argument]='fg={string}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[double-quoted-argument]='fg={string}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[dollar-quoted-argument]='fg={string}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[redirection]='fg={option},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[arg0]='fg={command},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[default]='fg={text}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[unknown-token]='fg={error},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[reserved-word]='fg={keyword},bold'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[suffix-alias]='fg={command},underline'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[single-hyphen-option]='fg={option}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[double-hyphen-option]='fg={option}'\n",
+                    "  ZSH_HIGHLIGHT_STYLES[comment]='fg={muted}'\n",
+                    "fi\n",
+                    "(( ${{+ZSH_AUTOSUGGEST_HIGHLIGHT_STYLE}} )) && ZSH_AUTOSUGGEST_HIGHLIGHT_STYLE='fg={muted}'\n",
+                ),
+                version = version,
+                user = c.user.hex(),
+                path = c.path.hex(),
+                branch = c.branch.hex(),
+                prompt = c.prompt.hex(),
+                muted = c.muted.hex(),
+                command = c.command.hex(),
+                keyword = c.keyword.hex(),
+                string = c.string.hex(),
+                option = c.option.hex(),
+                text = c.text.hex(),
+                error = c.error.hex(),
+            ),
+            Shell::Bash => format!(
+                concat!(
+                    "_tp_theme_version={version}\n",
+                    "# Generated by Terminal+ from the app theme\n",
+                    "_tp_branch_color='{branch}'\n",
+                    "PS1='\\n\\[\\033[38;2;{user}m\\]\\u\\[\\033[0m\\] \\[\\033[38;2;{path}m\\]\\w\\[\\033[0m\\]$(_tp_git_branch)\\n\\[\\033[38;2;{prompt}m\\]❯\\[\\033[0m\\] '\n",
+                ),
+                version = version,
+                user = c.user.sgr(),
+                path = c.path.sgr(),
+                branch = c.branch.sgr(),
+                prompt = c.prompt.sgr(),
+            ),
+            Shell::Fish => format!(
+                concat!(
+                    "set -g __terminal_plus_theme_version {version}\n",
+                    "# Generated by Terminal+ from the app theme\n",
+                    "set -g __terminal_plus_color_user {user}\n",
+                    "set -g __terminal_plus_color_path {path}\n",
+                    "set -g __terminal_plus_color_branch {branch}\n",
+                    "set -g __terminal_plus_color_prompt {prompt}\n",
+                    "set -g __terminal_plus_color_muted {muted}\n",
+                    "set -g fish_color_command {command} --bold\n",
+                    "set -g fish_color_keyword {keyword} --bold\n",
+                    "set -g fish_color_param {text}\n",
+                    "set -g fish_color_valid_path {path} --underline\n",
+                    "set -g fish_color_quote {string}\n",
+                    "set -g fish_color_redirection {option} --bold\n",
+                    "set -g fish_color_operator {option}\n",
+                    "set -g fish_color_option {option}\n",
+                    "set -g fish_color_error {error} --bold\n",
+                    "set -g fish_color_comment {muted}\n",
+                    "set -g fish_color_autosuggestion {muted}\n",
+                ),
+                version = version,
+                user = c.user.bare(),
+                path = c.path.bare(),
+                branch = c.branch.bare(),
+                prompt = c.prompt.bare(),
+                muted = c.muted.bare(),
+                command = c.command.bare(),
+                keyword = c.keyword.bare(),
+                string = c.string.bare(),
+                option = c.option.bare(),
+                text = c.text.bare(),
+                error = c.error.bare(),
+            ),
+            Shell::Nu => format!(
+                concat!(
+                    "{{version: \"{version}\"\n",
+                    " prompt: {{user: \"{user}\" path: \"{path}\" branch: \"{branch}\" prompt: \"{prompt}\" muted: \"{muted}\"}}\n",
+                    " color_config: {{\n",
+                    "  shape_internalcall: {{fg: \"{command}\" attr: \"b\"}}\n",
+                    "  shape_external: {{fg: \"{command}\" attr: \"b\"}}\n",
+                    "  shape_keyword: {{fg: \"{keyword}\" attr: \"b\"}}\n",
+                    "  shape_filepath: {{fg: \"{path}\" attr: \"u\"}}\n",
+                    "  shape_directory: {{fg: \"{path}\" attr: \"u\"}}\n",
+                    "  shape_globpattern: \"{option}\"\n",
+                    "  shape_string: \"{string}\"\n",
+                    "  shape_string_interpolation: \"{string}\"\n",
+                    "  shape_pipe: {{fg: \"{option}\" attr: \"b\"}}\n",
+                    "  shape_redirection: {{fg: \"{option}\" attr: \"b\"}}\n",
+                    "  shape_flag: \"{option}\"\n",
+                    "  shape_externalarg: \"{text}\"\n",
+                    "  shape_garbage: {{fg: \"{error}\" attr: \"b\"}}\n",
+                    "  hints: \"{muted}\"\n",
+                    " }}\n",
+                    "}}\n",
+                ),
+                version = version,
+                user = c.user.hex(),
+                path = c.path.hex(),
+                branch = c.branch.hex(),
+                prompt = c.prompt.hex(),
+                muted = c.muted.hex(),
+                command = c.command.hex(),
+                keyword = c.keyword.hex(),
+                string = c.string.hex(),
+                option = c.option.hex(),
+                text = c.text.hex(),
+                error = c.error.hex(),
+            ),
+        }
+    }
+
+    fn write_theme(self, home: &str, colors: &Colors) -> Result<(), String> {
+        let path = self.theme_path(home);
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create shell integration dir: {e}"))?;
+        }
+        let version = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        // Written aside and renamed so a shell never sources half a file. The
+        // name is unique to this write, so concurrent writes can't mix.
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let write = WRITES.fetch_add(1, Ordering::Relaxed);
+        let tmp = format!("{path}.{}-{write}.tmp", std::process::id());
+        let written = std::fs::write(&tmp, self.theme(colors, version))
+            .and_then(|_| std::fs::rename(&tmp, &path));
+        if written.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        written.map_err(|e| format!("Failed to write {path}: {e}"))
+    }
+
+    /// Write the default theme file if there is none yet, e.g. before the
+    /// frontend has sent its palette.
+    pub fn ensure_theme(self, home: &str) -> Result<(), String> {
+        if std::path::Path::new(&self.theme_path(home)).exists() {
+            return Ok(());
+        }
+        self.write_theme(home, &Colors::new(&PromptPalette::default())?)
+    }
+}
+
+/// Regenerate every shell's theme file from `palette`. Running sessions pick
+/// it up before their next prompt; new sessions start with it.
+pub fn apply_palette(palette: &PromptPalette) -> Result<(), String> {
+    let colors = Colors::new(palette)?;
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    for shell in Shell::ALL {
+        shell.write_theme(&home, &colors)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::types::ForegroundProcess;
+
+/// Describe a foreground process group leader.
+/// `shell_pid` is the session's shell, used to flag the idle-prompt case.
+pub fn foreground_process(pgrp: u32, shell_pid: Option<u32>) -> Option<ForegroundProcess> {
+    let (name, cmdline) = process_info(pgrp)?;
+    Some(ForegroundProcess {
+        pid: pgrp,
+        name,
+        cmdline,
+        is_shell: shell_pid == Some(pgrp),
+    })
+}
+
+/// Query the OS for a process's name and argv by PID.
+#[cfg(target_os = "linux")]
+fn process_info(pid: u32) -> Option<(String, Vec<String>)> {
+    let name = std::fs::read_to_string(format!("/proc/{pid}/comm")).ok()?;
+    let cmdline = std::fs::read(format!("/proc/{pid}/cmdline"))
+        .map(|raw| {
+            raw.split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect()
+        })
+        .unwrap_or_default();
+    Some((name.trim_end().to_string(), cmdline))
+}
+
+#[cfg(target_os = "macos")]
+fn process_info(pid: u32) -> Option<(String, Vec<String>)> {
+    // `comm` is the executable path, `args` the full command line
+    let ps = |field: &str| -> Option<String> {
+        let output = std::process::Command::new("ps")
+            .args(["-o", field, "-p", &pid.to_string()])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    let comm = ps("comm=")?;
+    let name = comm
+        .rsplit('/')
+        .next()
+        .unwrap_or(&comm)
+        .trim_start_matches('-');
+    let cmdline = ps("args=")
+        .map(|args| args.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    Some((name.to_string(), cmdline))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn process_info(_pid: u32) -> Option<(String, Vec<String>)> {
+    None
+}
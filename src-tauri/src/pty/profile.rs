@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Id of the profile used when `pty_create` is called without one.
+pub const DEFAULT_PROFILE_ID: &str = "default";
+
+/// How much of Terminal+'s shell integration a session gets. The scripts
+/// read it from `TERMINAL_PLUS_INTEGRATION`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrationLevel {
+    /// Our prompt, colors and `ls`/`grep` helpers, plus the marks.
+    #[default]
+    Full,
+    /// Only the OSC 7 / OSC 133 marks for cwd and command tracking; the
+    /// user's prompt (starship, powerlevel10k, ...) and aliases are kept.
+    MarksOnly,
+    /// No integration files at all.
+    Off,
+}
+
+impl IntegrationLevel {
+    /// Value of `TERMINAL_PLUS_INTEGRATION`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IntegrationLevel::Full => "full",
+            IntegrationLevel::MarksOnly => "marks_only",
+            IntegrationLevel::Off => "off",
+        }
+    }
+}
+
+/// A named launch configuration for a terminal session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellProfile {
+    pub id: String,
+    pub name: String,
+    /// Program to run. `None` means the user's `$SHELL`.
+    #[serde(default)]
+    pub program: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables, applied after the inherited ones.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Starting directory (`~` is expanded). Overridden by an explicit cwd.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Start the program as a login shell (`-l`). Unset means yes for known
+    /// shells and no for anything else, which may not understand `-l`.
+    #[serde(default)]
+    pub login: Option<bool>,
+    #[serde(default)]
+    pub integration: IntegrationLevel,
+}
+
+/// Shells that take `-l`, by executable name.
+const LOGIN_SHELLS: &[&str] = &["sh", "bash", "zsh", "fish", "ksh", "mksh", "dash", "nu"];
+
+#[derive(Debug, Default, Deserialize)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: Vec<ShellProfile>,
+}
+
+impl ShellProfile {
+    /// The built-in profile: `$SHELL -l`, matching the historical behaviour.
+    pub fn builtin_default() -> Self {
+        ShellProfile {
+            id: DEFAULT_PROFILE_ID.to_string(),
+            name: "Default Shell".to_string(),
+            program: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            login: Some(true),
+            integration: IntegrationLevel::Full,
+        }
+    }
+
+    /// An ad-hoc profile that runs `program` directly (e.g. `htop`), without a
+    /// login shell or shell integration.
+    pub fn command(program: String, args: Vec<String>) -> Self {
+        ShellProfile {
+            id: program.clone(),
+            name: program.clone(),
+            program: Some(program),
+            args,
+            env: HashMap::new(),
+            cwd: None,
+            login: Some(false),
+            integration: IntegrationLevel::Off,
+        }
+    }
+
+    /// Resolve the program path, falling back to `$SHELL` and then `/bin/zsh`.
+    pub fn program(&self) -> String {
+        self.program
+            .clone()
+            .unwrap_or_else(|| std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string()))
+    }
+
+    /// Whether to pass `-l`, defaulting by the program's name.
+    pub fn login(&self) -> bool {
+        self.login.unwrap_or_else(|| {
+            let program = self.program();
+            let name = program.rsplit('/').next().unwrap_or(&program);
+            LOGIN_SHELLS.contains(&name)
+        })
+    }
+
+    /// Resolve the starting directory, expanding `~` and `~/...`. Other
+    /// users' homes (`~alice`) are left as they are.
+    pub fn cwd(&self, home: &str) -> Option<String> {
+        self.cwd.as_ref().map(|cwd| {
+            let home_relative = cwd
+                .strip_prefix('~')
+                .filter(|rest| rest.is_empty() || rest.starts_with('/'));
+            match home_relative {
+                Some(rest) => format!("{home}{rest}"),
+                None => cwd.clone(),
+            }
+        })
+    }
+}
+
+fn profiles_path() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    format!("{home}/.terminal-plus/profiles.json")
+}
+
+/// Load the profiles from `~/.terminal-plus/profiles.json`.
+/// The built-in default is always present unless the file overrides the `default` id.
+pub fn load_profiles() -> Result<Vec<ShellProfile>, String> {
+    let path = profiles_path();
+    let file = match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str::<ProfilesFile>(&contents)
+            .map_err(|e| format!("Invalid profiles file {path}: {e}"))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ProfilesFile::default(),
+        Err(e) => return Err(format!("Failed to read profiles file {path}: {e}")),
+    };
+
+    let mut profiles = file.profiles;
+    if !profiles.iter().any(|p| p.id == DEFAULT_PROFILE_ID) {
+        profiles.insert(0, ShellProfile::builtin_default());
+    }
+    Ok(profiles)
+}
+
+/// Look up a profile by id, defaulting to the `default` profile.
+pub fn find_profile(profile_id: Option<&str>) -> Result<ShellProfile, String> {
+    let id = profile_id.unwrap_or(DEFAULT_PROFILE_ID);
+    load_profiles()?
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Profile {id} not found"))
+}
@@ -0,0 +1,201 @@
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use super::types::RecordingInfo;
+
+/// Where recordings go when the caller doesn't pick a path:
+/// `~/.terminal-plus/recordings/<session>-<unix time>.cast`.
+pub fn default_path(session_id: &str) -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("{home}/.terminal-plus/recordings/{session_id}-{now}.cast")
+}
+
+enum Record {
+    Output(Instant, Vec<u8>),
+    Resize(Instant, u16, u16),
+}
+
+/// asciicast v2 header line.
+#[derive(Serialize)]
+struct Header {
+    version: u8,
+    width: u16,
+    height: u16,
+    timestamp: u64,
+    env: Env,
+}
+
+#[derive(Serialize)]
+struct Env {
+    #[serde(rename = "SHELL")]
+    shell: String,
+    #[serde(rename = "TERM")]
+    term: &'static str,
+}
+
+struct Active {
+    path: String,
+    started_at: u64,
+    started: Instant,
+    tx: Sender<Record>,
+    writer: JoinHandle<()>,
+}
+
+/// Tees a session's output and resizes into an asciicast v2 (`.cast`) file.
+///
+/// The reader thread only timestamps and queues each read; encoding and
+/// file I/O happen on a separate writer thread, so recording doesn't hold
+/// up the output channel.
+pub struct Recorder {
+    shell: String,
+    active: Mutex<Option<Active>>,
+}
+
+impl Recorder {
+    pub fn new(shell: String) -> Self {
+        Recorder {
+            shell,
+            active: Mutex::new(None),
+        }
+    }
+
+    pub fn start(&self, path: String, cols: u16, rows: u16) -> Result<RecordingInfo, String> {
+        let mut active = self.active.lock();
+        if let Some(current) = active.as_ref() {
+            return Err(format!("Already recording to {}", current.path));
+        }
+        if let Some(parent) = std::path::Path::new(&path).parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create recording dir: {e}"))?;
+        }
+        let file = File::create(&path).map_err(|e| format!("Failed to create {path}: {e}"))?;
+        let mut out = BufWriter::new(file);
+
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let header = Header {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: started_at,
+            env: Env {
+                shell: self.shell.clone(),
+                term: "xterm-256color",
+            },
+        };
+        serde_json::to_writer(&mut out, &header)
+            .map_err(|e| format!("Failed to write cast header: {e}"))?;
+        writeln!(out).map_err(|e| format!("Failed to write cast header: {e}"))?;
+
+        let started = Instant::now();
+        let (tx, rx) = mpsc::channel();
+        let writer = std::thread::spawn(move || write_loop(out, started, rx));
+        *active = Some(Active {
+            path: path.clone(),
+            started_at,
+            started,
+            tx,
+            writer,
+        });
+        Ok(RecordingInfo {
+            path,
+            started_at,
+            duration_ms: 0,
+        })
+    }
+
+    /// Finish the recording, waiting until everything is on disk.
+    /// Returns `None` if nothing was being recorded.
+    pub fn stop(&self) -> Option<RecordingInfo> {
+        let active = self.active.lock().take()?;
+        let info = RecordingInfo {
+            path: active.path,
+            started_at: active.started_at,
+            duration_ms: active.started.elapsed().as_millis() as u64,
+        };
+        // Closing the channel ends the writer thread
+        drop(active.tx);
+        let _ = active.writer.join();
+        Some(info)
+    }
+
+    pub fn output(&self, data: &[u8]) {
+        if let Some(active) = self.active.lock().as_ref() {
+            let _ = active
+                .tx
+                .send(Record::Output(Instant::now(), data.to_vec()));
+        }
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) {
+        if let Some(active) = self.active.lock().as_ref() {
+            let _ = active.tx.send(Record::Resize(Instant::now(), cols, rows));
+        }
+    }
+}
+
+fn write_loop(mut out: BufWriter<File>, started: Instant, rx: mpsc::Receiver<Record>) {
+    // Output records must be valid UTF-8, but a read can end mid-character;
+    // hold back the incomplete tail until the next read completes it
+    let mut carry: Vec<u8> = Vec::new();
+    for record in rx {
+        let (at, code, data) = match record {
+            Record::Output(at, bytes) => {
+                carry.extend_from_slice(&bytes);
+                let split = utf8_boundary(&carry);
+                let rest = carry.split_off(split);
+                let text = String::from_utf8_lossy(&carry).into_owned();
+                carry = rest;
+                if text.is_empty() {
+                    continue;
+                }
+                (at, "o", text)
+            }
+            Record::Resize(at, cols, rows) => (at, "r", format!("{cols}x{rows}")),
+        };
+        let time = at.saturating_duration_since(started).as_secs_f64();
+        if serde_json::to_writer(&mut out, &(time, code, data)).is_err() || writeln!(out).is_err() {
+            return;
+        }
+    }
+    if !carry.is_empty() {
+        let time = started.elapsed().as_secs_f64();
+        let text = String::from_utf8_lossy(&carry).into_owned();
+        let _ = serde_json::to_writer(&mut out, &(time, "o", text));
+        let _ = writeln!(out);
+    }
+    let _ = out.flush();
+}
+
+/// Length of the longest prefix of `bytes` that doesn't end inside a
+/// UTF-8 sequence that could still be completed.
+fn utf8_boundary(bytes: &[u8]) -> usize {
+    // A UTF-8 sequence is at most 4 bytes, so only the last 3 can be a partial one
+    for back in 1..=bytes.len().min(3) {
+        let i = bytes.len() - back;
+        let b = bytes[i];
+        if b & 0xC0 == 0x80 {
+            // Continuation byte; keep looking for the lead byte
+            continue;
+        }
+        let needed = match b {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => return bytes.len(),
+        };
+        return if back < needed { i } else { bytes.len() };
+    }
+    bytes.len()
+}
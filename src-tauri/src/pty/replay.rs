@@ -0,0 +1,336 @@
+use parking_lot::{Condvar, Mutex};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter};
+
+use super::backend::SessionBackend;
+use super::output::{FlowControl, OutputStream};
+use super::types::{PtyTeardown, ReplayState, TeardownOutcome};
+
+/// Full reset, sent before redrawing from the start on a seek.
+const RESET: &[u8] = b"\x1bc";
+
+/// A change requested through the `pty_replay_*` commands.
+#[derive(Debug, Clone, Copy)]
+pub enum ReplayControl {
+    Pause,
+    /// Continue playing; restarts from the beginning if the replay had finished.
+    Resume,
+    /// Jump to a position in the recording.
+    Seek(Duration),
+    /// Playback rate, 1.0 being the original timing.
+    Speed(f64),
+}
+
+#[derive(Deserialize)]
+struct CastHeader {
+    version: u32,
+    width: u16,
+    height: u16,
+}
+
+enum EventKind {
+    Output(String),
+    Resize(u16, u16),
+}
+
+struct CastEvent {
+    /// Seconds since the start of the recording.
+    time: f64,
+    kind: EventKind,
+}
+
+/// A parsed asciicast v2 file. Only output and resize events are kept.
+struct Cast {
+    width: u16,
+    height: u16,
+    events: Vec<CastEvent>,
+}
+
+fn parse_cast(contents: &str) -> Result<Cast, String> {
+    let mut lines = contents.lines();
+    let header: CastHeader = lines
+        .next()
+        .ok_or("Empty cast file")
+        .and_then(|line| serde_json::from_str(line).map_err(|_| "Invalid cast header"))?;
+    if header.version != 2 {
+        return Err(format!("Unsupported asciicast version {}", header.version));
+    }
+    let mut events = Vec::new();
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let (time, code, data): (f64, String, String) =
+            serde_json::from_str(line).map_err(|e| format!("Invalid cast event: {e}"))?;
+        let kind = match code.as_str() {
+            "o" => EventKind::Output(data),
+            "r" => match data
+                .split_once('x')
+                .and_then(|(c, r)| Some((c.parse().ok()?, r.parse().ok()?)))
+            {
+                Some((cols, rows)) => EventKind::Resize(cols, rows),
+                None => continue,
+            },
+            // Input, markers and anything newer than v2
+            _ => continue,
+        };
+        events.push(CastEvent { time, kind });
+    }
+    Ok(Cast {
+        width: header.width,
+        height: header.height,
+        events,
+    })
+}
+
+struct Playback {
+    /// Recording time at `base_instant`.
+    base_pos: f64,
+    base_instant: Instant,
+    speed: f64,
+    paused: bool,
+    /// Index of the next event to play.
+    cursor: usize,
+    cols: u16,
+    rows: u16,
+    /// The player must clear the screen and replay everything before `cursor`.
+    redraw: bool,
+    closed: bool,
+}
+
+struct Shared {
+    cast: Cast,
+    duration: f64,
+    playback: Mutex<Playback>,
+    cond: Condvar,
+    app: AppHandle,
+    state_event: String,
+}
+
+impl Shared {
+    fn position(&self, pb: &Playback) -> f64 {
+        let pos = if pb.paused {
+            pb.base_pos
+        } else {
+            pb.base_pos + pb.base_instant.elapsed().as_secs_f64() * pb.speed
+        };
+        pos.min(self.duration)
+    }
+
+    fn state(&self, pb: &Playback) -> ReplayState {
+        ReplayState {
+            position_ms: (self.position(pb) * 1000.0) as u64,
+            duration_ms: (self.duration * 1000.0) as u64,
+            speed: pb.speed,
+            paused: pb.paused,
+            finished: pb.cursor >= self.cast.events.len(),
+            cols: pb.cols,
+            rows: pb.rows,
+        }
+    }
+
+    fn emit_state(&self, state: &ReplayState) {
+        let _ = self.app.emit(&self.state_event, state);
+    }
+
+    fn seek(&self, pb: &mut Playback, pos: f64) {
+        let pos = pos.clamp(0.0, self.duration);
+        pb.base_pos = pos;
+        pb.base_instant = Instant::now();
+        pb.cursor = self.cast.events.partition_point(|e| e.time <= pos);
+        // The recorded size at that point
+        (pb.cols, pb.rows) = self.cast.events[..pb.cursor]
+            .iter()
+            .rev()
+            .find_map(|e| match e.kind {
+                EventKind::Resize(cols, rows) => Some((cols, rows)),
+                EventKind::Output(_) => None,
+            })
+            .unwrap_or((self.cast.width, self.cast.height));
+        pb.redraw = true;
+    }
+}
+
+/// A read-only pseudo-session that plays an asciicast recording back over
+/// the normal output channel, with the original timing.
+pub struct ReplaySession {
+    shared: Arc<Shared>,
+    flow: Arc<FlowControl>,
+    player: Mutex<Option<JoinHandle<()>>>,
+    home: String,
+}
+
+impl ReplaySession {
+    pub fn open(
+        app: AppHandle,
+        session_id: String,
+        path: &str,
+        on_output: Channel,
+    ) -> Result<(Self, ReplayState), String> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+        let cast = parse_cast(&contents)?;
+        let duration = cast.events.last().map(|e| e.time).unwrap_or(0.0);
+        let shared = Arc::new(Shared {
+            duration,
+            playback: Mutex::new(Playback {
+                base_pos: 0.0,
+                base_instant: Instant::now(),
+                speed: 1.0,
+                paused: false,
+                cursor: 0,
+                cols: cast.width,
+                rows: cast.height,
+                redraw: false,
+                closed: false,
+            }),
+            cast,
+            cond: Condvar::new(),
+            app,
+            state_event: format!("pty-replay-state-{session_id}"),
+        });
+        let flow = Arc::new(FlowControl::default());
+        let output = OutputStream::new(on_output, flow.clone());
+        let player_shared = shared.clone();
+        let player = std::thread::spawn(move || play(&player_shared, output));
+        let state = shared.state(&shared.playback.lock());
+        Ok((
+            ReplaySession {
+                shared,
+                flow,
+                player: Mutex::new(Some(player)),
+                home: std::env::var("HOME").unwrap_or_else(|_| "/".to_string()),
+            },
+            state,
+        ))
+    }
+}
+
+/// Player thread: sends each event once its time comes, re-planning whenever
+/// a control changes the timeline.
+fn play(shared: &Shared, output: OutputStream) {
+    let events = &shared.cast.events;
+    let mut pb = shared.playback.lock();
+    loop {
+        if pb.closed {
+            break;
+        }
+        if pb.redraw {
+            pb.redraw = false;
+            let mut screen = RESET.to_vec();
+            for event in &events[..pb.cursor] {
+                if let EventKind::Output(data) = &event.kind {
+                    screen.extend_from_slice(data.as_bytes());
+                }
+            }
+            drop(pb);
+            output.push(&screen);
+            pb = shared.playback.lock();
+            continue;
+        }
+        if pb.paused || pb.cursor >= events.len() {
+            shared.cond.wait(&mut pb);
+            continue;
+        }
+
+        let event = &events[pb.cursor];
+        let delay = ((event.time - pb.base_pos) / pb.speed).max(0.0);
+        let due = pb.base_instant + Duration::from_secs_f64(delay);
+        if Instant::now() < due {
+            shared.cond.wait_until(&mut pb, due);
+            continue;
+        }
+
+        pb.cursor += 1;
+        let finished = pb.cursor >= events.len();
+        match &event.kind {
+            EventKind::Output(data) => {
+                drop(pb);
+                output.wait_for_capacity();
+                output.push(data.as_bytes());
+                pb = shared.playback.lock();
+                if finished {
+                    shared.emit_state(&shared.state(&pb));
+                }
+            }
+            EventKind::Resize(cols, rows) => {
+                pb.cols = *cols;
+                pb.rows = *rows;
+                shared.emit_state(&shared.state(&pb));
+            }
+        }
+    }
+}
+
+impl SessionBackend for ReplaySession {
+    fn write(&self, _data: &[u8]) -> Result<(), String> {
+        Err("Replay sessions are read-only".to_string())
+    }
+
+    fn ack(&self, bytes: usize) {
+        self.flow.ack(bytes);
+    }
+
+    /// The recording has its own size; the pane just shows it.
+    fn resize(&self, _cols: u16, _rows: u16) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn shutdown(&self, _grace: Duration) -> PtyTeardown {
+        let started = Instant::now();
+        self.shared.playback.lock().closed = true;
+        self.shared.cond.notify_all();
+        self.flow.close();
+        if let Some(player) = self.player.lock().take() {
+            let _ = player.join();
+        }
+        PtyTeardown {
+            outcome: TeardownOutcome::Closed,
+            exit: None,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    fn get_cwd(&self) -> String {
+        self.home.clone()
+    }
+
+    fn control_replay(&self, control: ReplayControl) -> Result<ReplayState, String> {
+        let shared = &self.shared;
+        let mut pb = shared.playback.lock();
+        match control {
+            ReplayControl::Pause => {
+                if !pb.paused {
+                    pb.base_pos = shared.position(&pb);
+                    pb.paused = true;
+                }
+            }
+            ReplayControl::Resume => {
+                if pb.cursor >= shared.cast.events.len() {
+                    shared.seek(&mut pb, 0.0);
+                }
+                if pb.paused {
+                    pb.base_instant = Instant::now();
+                    pb.paused = false;
+                }
+            }
+            ReplayControl::Seek(position) => {
+                shared.seek(&mut pb, position.as_secs_f64());
+            }
+            ReplayControl::Speed(speed) => {
+                if !(speed.is_finite() && speed > 0.0) {
+                    return Err(format!("Invalid replay speed {speed}"));
+                }
+                pb.base_pos = shared.position(&pb);
+                pb.base_instant = Instant::now();
+                pb.speed = speed;
+            }
+        }
+        shared.cond.notify_all();
+        let state = shared.state(&pb);
+        drop(pb);
+        shared.emit_state(&state);
+        Ok(state)
+    }
+}
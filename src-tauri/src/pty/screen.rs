@@ -0,0 +1,765 @@
+use std::collections::VecDeque;
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Parser, Perform};
+
+/// Lines kept above the visible screen.
+pub const SCROLLBACK_LINES: usize = 10_000;
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Color {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Style {
+    fg: Color,
+    bg: Color,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    blink: bool,
+    inverse: bool,
+    hidden: bool,
+    strikethrough: bool,
+}
+
+impl Style {
+    /// SGR sequence that switches from the default style to this one.
+    fn sgr(&self) -> String {
+        let mut codes: Vec<String> = Vec::new();
+        for (on, code) in [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.blink, "5"),
+            (self.inverse, "7"),
+            (self.hidden, "8"),
+            (self.strikethrough, "9"),
+        ] {
+            if on {
+                codes.push(code.to_string());
+            }
+        }
+        for (color, base) in [(self.fg, 30), (self.bg, 40)] {
+            match color {
+                Color::Default => {}
+                Color::Indexed(i) if i < 8 => codes.push((base + i as u32).to_string()),
+                Color::Indexed(i) if i < 16 => codes.push((base + 60 + i as u32 - 8).to_string()),
+                Color::Indexed(i) => codes.push(format!("{};5;{i}", base + 8)),
+                Color::Rgb(r, g, b) => codes.push(format!("{};2;{r};{g};{b}", base + 8)),
+            }
+        }
+        if codes.is_empty() {
+            return "\x1b[0m".to_string();
+        }
+        format!("\x1b[0;{}m", codes.join(";"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cell {
+    c: char,
+    style: Style,
+    /// Right half of a double-width character.
+    spacer: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            c: ' ',
+            style: Style::default(),
+            spacer: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Row {
+    cells: Vec<Cell>,
+    /// The line continues on the next row (soft wrap).
+    pub wrapped: bool,
+}
+
+impl Row {
+    fn new(cols: usize, blank: Cell) -> Self {
+        Row {
+            cells: vec![blank; cols],
+            wrapped: false,
+        }
+    }
+
+    fn resize(&mut self, cols: usize) {
+        self.cells.resize(cols, Cell::default());
+    }
+
+    pub fn text(&self) -> String {
+        let mut text: String = self
+            .cells
+            .iter()
+            .filter(|cell| !cell.spacer)
+            .map(|cell| cell.c)
+            .collect();
+        text.truncate(text.trim_end_matches(' ').len());
+        text
+    }
+
+    /// Plain text, keeping trailing blanks when the line continues on the next row.
+    pub fn joinable_text(&self) -> String {
+        if !self.wrapped {
+            return self.text();
+        }
+        self.cells
+            .iter()
+            .filter(|cell| !cell.spacer)
+            .map(|cell| cell.c)
+            .collect()
+    }
+
+    /// The row as text with SGR sequences for its styling.
+    pub fn styled_text(&self) -> String {
+        let end = self
+            .cells
+            .iter()
+            .rposition(|cell| cell.c != ' ' || cell.style != Style::default())
+            .map_or(0, |i| i + 1);
+        let mut out = String::new();
+        let mut current = Style::default();
+        for cell in self.cells[..end].iter().filter(|cell| !cell.spacer) {
+            if cell.style != current {
+                current = cell.style;
+                out.push_str(&current.sgr());
+            }
+            out.push(cell.c);
+        }
+        if current != Style::default() {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct SavedCursor {
+    x: usize,
+    y: usize,
+    style: Style,
+}
+
+/// A headless terminal: the grid and scrollback that xterm.js would show,
+/// rebuilt from the raw output so the backend can answer what a pane shows.
+pub struct Screen {
+    cols: usize,
+    rows: usize,
+    lines: Vec<Row>,
+    /// The primary screen's lines while the alternate screen is active.
+    primary: Option<Vec<Row>>,
+    scrollback: VecDeque<Row>,
+    /// Rows that have left the scrollback, counting from the start of the session.
+    dropped: u64,
+    /// Rows dropped since the last [`Screen::take_evicted`].
+    evicted: Vec<Row>,
+    x: usize,
+    y: usize,
+    /// The cursor is past the last column; the next print wraps first.
+    pending_wrap: bool,
+    style: Style,
+    /// Scroll region, inclusive.
+    top: usize,
+    bottom: usize,
+    saved: SavedCursor,
+    last_char: Option<char>,
+    /// A BEL arrived since the last [`Screen::take_bell`].
+    bell: bool,
+}
+
+impl Screen {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        let (cols, rows) = (cols.max(1) as usize, rows.max(1) as usize);
+        Screen {
+            cols,
+            rows,
+            lines: vec![Row::new(cols, Cell::default()); rows],
+            primary: None,
+            scrollback: VecDeque::new(),
+            dropped: 0,
+            evicted: Vec::new(),
+            x: 0,
+            y: 0,
+            pending_wrap: false,
+            style: Style::default(),
+            top: 0,
+            bottom: rows - 1,
+            saved: SavedCursor::default(),
+            last_char: None,
+            bell: false,
+        }
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols as u16, self.rows as u16)
+    }
+
+    /// Cursor position as (row, column) on the visible screen.
+    pub fn cursor(&self) -> (u16, u16) {
+        (self.y as u16, self.x as u16)
+    }
+
+    pub fn scrollback_len(&self) -> usize {
+        self.scrollback.len()
+    }
+
+    /// Scrollback plus visible lines.
+    pub fn total_lines(&self) -> usize {
+        self.scrollback.len() + self.rows
+    }
+
+    /// Row number, since the session started, of the oldest scrollback line.
+    pub fn first_line(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Rows that fell off the scrollback (or were cleared) since the last call, oldest first.
+    pub fn take_evicted(&mut self) -> Vec<Row> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell)
+    }
+
+    /// Line `index` counting from the oldest scrollback line.
+    pub fn line(&self, index: usize) -> Option<&Row> {
+        match index.checked_sub(self.scrollback.len()) {
+            None => self.scrollback.get(index),
+            Some(i) => self.lines.get(i),
+        }
+    }
+
+    pub fn resize(&mut self, cols: u16, rows: u16) {
+        let (cols, rows) = (cols.max(1) as usize, rows.max(1) as usize);
+        for row in self
+            .lines
+            .iter_mut()
+            .chain(self.primary.iter_mut().flatten())
+        {
+            row.resize(cols);
+        }
+        if rows < self.rows {
+            // Drop blank lines below the cursor first, then push the top into scrollback
+            let mut excess = self.rows - rows;
+            while excess > 0
+                && self.lines.len() - 1 > self.y
+                && self.lines.last().is_some_and(|r| r.text().is_empty())
+            {
+                self.lines.pop();
+                excess -= 1;
+            }
+            for _ in 0..excess {
+                let row = self.lines.remove(0);
+                self.push_scrollback(row);
+                self.y = self.y.saturating_sub(1);
+            }
+        } else {
+            self.lines.resize(rows, Row::new(cols, Cell::default()));
+        }
+        if let Some(primary) = self.primary.as_mut() {
+            primary.resize(rows, Row::new(cols, Cell::default()));
+        }
+        self.cols = cols;
+        self.rows = rows;
+        self.top = 0;
+        self.bottom = rows - 1;
+        self.x = self.x.min(cols - 1);
+        self.y = self.y.min(rows - 1);
+        self.pending_wrap = false;
+    }
+
+    fn alt_active(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    fn push_scrollback(&mut self, row: Row) {
+        if self.alt_active() {
+            return;
+        }
+        self.scrollback.push_back(row);
+        if self.scrollback.len() > SCROLLBACK_LINES {
+            self.evict(1);
+        }
+    }
+
+    fn evict(&mut self, n: usize) {
+        self.dropped += n as u64;
+        self.evicted.extend(self.scrollback.drain(..n));
+    }
+
+    /// Cell used for erasing: blank, keeping the current background (BCE).
+    fn blank(&self) -> Cell {
+        Cell {
+            style: Style {
+                bg: self.style.bg,
+                ..Style::default()
+            },
+            ..Cell::default()
+        }
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        for _ in 0..n.min(self.bottom - self.top + 1) {
+            let row = self.lines.remove(self.top);
+            if self.top == 0 {
+                self.push_scrollback(row);
+            }
+            self.lines
+                .insert(self.bottom, Row::new(self.cols, self.blank()));
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        for _ in 0..n.min(self.bottom - self.top + 1) {
+            self.lines.remove(self.bottom);
+            self.lines
+                .insert(self.top, Row::new(self.cols, self.blank()));
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.pending_wrap = false;
+        if self.y == self.bottom {
+            self.scroll_up(1);
+        } else if self.y < self.rows - 1 {
+            self.y += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.pending_wrap = false;
+        if self.y == self.top {
+            self.scroll_down(1);
+        } else if self.y > 0 {
+            self.y -= 1;
+        }
+    }
+
+    fn wrap(&mut self) {
+        self.lines[self.y].wrapped = true;
+        self.x = 0;
+        self.linefeed();
+    }
+
+    fn put_char(&mut self, c: char) {
+        let width = match c.width() {
+            Some(w) if w > 0 => w,
+            // Combining marks and other zero-width characters
+            _ => return,
+        };
+        if self.pending_wrap || (width == 2 && self.x + 1 >= self.cols) {
+            self.wrap();
+        }
+        let style = self.style;
+        let row = &mut self.lines[self.y];
+        row.cells[self.x] = Cell {
+            c,
+            style,
+            spacer: false,
+        };
+        if width == 2 && self.x + 1 < self.cols {
+            row.cells[self.x + 1] = Cell {
+                c: ' ',
+                style,
+                spacer: true,
+            };
+        }
+        self.x += width;
+        if self.x >= self.cols {
+            self.x = self.cols - 1;
+            self.pending_wrap = true;
+        }
+        self.last_char = Some(c);
+    }
+
+    fn move_to(&mut self, x: usize, y: usize) {
+        self.x = x.min(self.cols - 1);
+        self.y = y.min(self.rows - 1);
+        self.pending_wrap = false;
+    }
+
+    fn erase_cells(&mut self, y: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        let row = &mut self.lines[y];
+        let to = to.min(row.cells.len());
+        for cell in &mut row.cells[from.min(to)..to] {
+            *cell = blank;
+        }
+        if to == self.cols {
+            row.wrapped = false;
+        }
+    }
+
+    fn erase_display(&mut self, mode: u16) {
+        match mode {
+            0 => {
+                self.erase_cells(self.y, self.x, self.cols);
+                for y in self.y + 1..self.rows {
+                    self.erase_cells(y, 0, self.cols);
+                }
+            }
+            1 => {
+                for y in 0..self.y {
+                    self.erase_cells(y, 0, self.cols);
+                }
+                self.erase_cells(self.y, 0, self.x + 1);
+            }
+            2 => {
+                for y in 0..self.rows {
+                    self.erase_cells(y, 0, self.cols);
+                }
+            }
+            3 => self.evict(self.scrollback.len()),
+            _ => {}
+        }
+    }
+
+    fn erase_line(&mut self, mode: u16) {
+        match mode {
+            0 => self.erase_cells(self.y, self.x, self.cols),
+            1 => self.erase_cells(self.y, 0, self.x + 1),
+            2 => self.erase_cells(self.y, 0, self.cols),
+            _ => {}
+        }
+    }
+
+    fn insert_cells(&mut self, n: usize) {
+        let blank = self.blank();
+        let x = self.x;
+        let cells = &mut self.lines[self.y].cells;
+        for _ in 0..n.min(cells.len() - x) {
+            cells.pop();
+            cells.insert(x, blank);
+        }
+    }
+
+    fn delete_cells(&mut self, n: usize) {
+        let blank = self.blank();
+        let x = self.x;
+        let cells = &mut self.lines[self.y].cells;
+        for _ in 0..n.min(cells.len() - x) {
+            cells.remove(x);
+            cells.push(blank);
+        }
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        if self.y < self.top || self.y > self.bottom {
+            return;
+        }
+        for _ in 0..n.min(self.bottom - self.y + 1) {
+            self.lines.remove(self.bottom);
+            self.lines.insert(self.y, Row::new(self.cols, self.blank()));
+        }
+        self.x = 0;
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        if self.y < self.top || self.y > self.bottom {
+            return;
+        }
+        for _ in 0..n.min(self.bottom - self.y + 1) {
+            self.lines.remove(self.y);
+            self.lines
+                .insert(self.bottom, Row::new(self.cols, self.blank()));
+        }
+        self.x = 0;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = SavedCursor {
+            x: self.x,
+            y: self.y,
+            style: self.style,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        let saved = self.saved;
+        self.style = saved.style;
+        self.move_to(saved.x, saved.y);
+    }
+
+    fn enter_alt_screen(&mut self) {
+        if self.alt_active() {
+            return;
+        }
+        let alt = vec![Row::new(self.cols, Cell::default()); self.rows];
+        self.primary = Some(std::mem::replace(&mut self.lines, alt));
+    }
+
+    fn leave_alt_screen(&mut self) {
+        if let Some(primary) = self.primary.take() {
+            self.lines = primary;
+        }
+    }
+
+    fn reset(&mut self) {
+        let scrollback = std::mem::take(&mut self.scrollback);
+        let evicted = std::mem::take(&mut self.evicted);
+        let dropped = self.dropped;
+        *self = Screen::new(self.cols as u16, self.rows as u16);
+        self.scrollback = scrollback;
+        self.evicted = evicted;
+        self.dropped = dropped;
+    }
+
+    fn set_mode(&mut self, private: bool, params: &Params, on: bool) {
+        if !private {
+            return;
+        }
+        for param in params.iter() {
+            match param[0] {
+                1049 => {
+                    if on {
+                        self.save_cursor();
+                        self.enter_alt_screen();
+                        self.erase_display(2);
+                    } else {
+                        self.leave_alt_screen();
+                        self.restore_cursor();
+                    }
+                }
+                47 | 1047 => {
+                    if on {
+                        self.enter_alt_screen();
+                    } else {
+                        self.leave_alt_screen();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_style(&mut self, params: &Params) {
+        let mut iter = params.iter();
+        if params.is_empty() {
+            self.style = Style::default();
+            return;
+        }
+        while let Some(param) = iter.next() {
+            let s = &mut self.style;
+            match param[0] {
+                0 => *s = Style::default(),
+                1 => s.bold = true,
+                2 => s.dim = true,
+                3 => s.italic = true,
+                4 => s.underline = param.get(1) != Some(&0),
+                5 | 6 => s.blink = true,
+                7 => s.inverse = true,
+                8 => s.hidden = true,
+                9 => s.strikethrough = true,
+                21 => s.underline = true,
+                22 => {
+                    s.bold = false;
+                    s.dim = false;
+                }
+                23 => s.italic = false,
+                24 => s.underline = false,
+                25 => s.blink = false,
+                27 => s.inverse = false,
+                28 => s.hidden = false,
+                29 => s.strikethrough = false,
+                n @ 30..=37 => s.fg = Color::Indexed((n - 30) as u8),
+                38 => s.fg = extended_color(param, &mut iter),
+                39 => s.fg = Color::Default,
+                n @ 40..=47 => s.bg = Color::Indexed((n - 40) as u8),
+                48 => s.bg = extended_color(param, &mut iter),
+                49 => s.bg = Color::Default,
+                n @ 90..=97 => s.fg = Color::Indexed((n - 90 + 8) as u8),
+                n @ 100..=107 => s.bg = Color::Indexed((n - 100 + 8) as u8),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parse the color after a 38/48, given either as sub-parameters
+/// (`38:2::r:g:b`, `38:5:n`) or as following parameters (`38;5;n`).
+fn extended_color<'a>(param: &[u16], iter: &mut impl Iterator<Item = &'a [u16]>) -> Color {
+    let mut next = |sub: &mut std::slice::Iter<u16>| -> Option<u16> {
+        match sub.next() {
+            Some(v) => Some(*v),
+            None => iter.next().map(|p| p[0]),
+        }
+    };
+    let mut sub = param[1..].iter();
+    let from_subparams = param.len() > 1;
+    match next(&mut sub) {
+        Some(5) => next(&mut sub).map_or(Color::Default, |i| Color::Indexed(i as u8)),
+        Some(2) => {
+            // The colon form may carry a color space id before r:g:b
+            if from_subparams && param.len() > 5 {
+                sub.next();
+            }
+            let r = next(&mut sub).unwrap_or(0) as u8;
+            let g = next(&mut sub).unwrap_or(0) as u8;
+            let b = next(&mut sub).unwrap_or(0) as u8;
+            Color::Rgb(r, g, b)
+        }
+        _ => Color::Default,
+    }
+}
+
+/// First parameter, with 0 or missing meaning `default`.
+fn arg(params: &Params, index: usize, default: usize) -> usize {
+    match params.iter().nth(index).map(|p| p[0]) {
+        None | Some(0) => default,
+        Some(n) => n as usize,
+    }
+}
+
+impl Perform for Screen {
+    fn print(&mut self, c: char) {
+        self.put_char(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        match byte {
+            // BEL
+            0x07 => self.bell = true,
+            // BS
+            0x08 => {
+                self.x = self.x.saturating_sub(1);
+                self.pending_wrap = false;
+            }
+            // HT
+            0x09 => {
+                let next = (self.x / TAB_WIDTH + 1) * TAB_WIDTH;
+                self.move_to(next, self.y);
+            }
+            // LF, VT, FF
+            0x0a..=0x0c => self.linefeed(),
+            // CR
+            0x0d => {
+                self.x = 0;
+                self.pending_wrap = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        if ignore {
+            return;
+        }
+        let private = intermediates.first() == Some(&b'?');
+        if !intermediates.is_empty() && !private {
+            return;
+        }
+        let n = arg(params, 0, 1);
+        match action {
+            'A' => self.move_to(self.x, self.y.saturating_sub(n)),
+            'B' | 'e' => self.move_to(self.x, self.y + n),
+            'C' | 'a' => self.move_to(self.x + n, self.y),
+            'D' => self.move_to(self.x.saturating_sub(n), self.y),
+            'E' => self.move_to(0, self.y + n),
+            'F' => self.move_to(0, self.y.saturating_sub(n)),
+            'G' | '`' => self.move_to(n - 1, self.y),
+            'd' => self.move_to(self.x, n - 1),
+            'H' | 'f' => self.move_to(arg(params, 1, 1) - 1, n - 1),
+            'J' if !private => self.erase_display(params.iter().next().map_or(0, |p| p[0])),
+            'K' if !private => self.erase_line(params.iter().next().map_or(0, |p| p[0])),
+            '@' => self.insert_cells(n),
+            'P' => self.delete_cells(n),
+            'X' => {
+                let (x, y) = (self.x, self.y);
+                self.erase_cells(y, x, x + n);
+            }
+            'L' => self.insert_lines(n),
+            'M' => self.delete_lines(n),
+            'S' => self.scroll_up(n),
+            'T' => self.scroll_down(n),
+            'b' => {
+                if let Some(c) = self.last_char {
+                    for _ in 0..n.min(self.cols * self.rows) {
+                        self.put_char(c);
+                    }
+                }
+            }
+            'm' if !private => self.set_style(params),
+            'h' => self.set_mode(private, params, true),
+            'l' => self.set_mode(private, params, false),
+            'r' if !private => {
+                let top = arg(params, 0, 1) - 1;
+                let bottom = arg(params, 1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.top = top;
+                    self.bottom = bottom;
+                    self.move_to(0, 0);
+                }
+            }
+            's' if !private => self.save_cursor(),
+            'u' if !private => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        // Charset designations and the like
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.x = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'c' => self.reset(),
+            _ => {}
+        }
+    }
+}
+
+/// A [`Screen`] with the parser that feeds it.
+pub struct ScreenModel {
+    parser: Parser,
+    pub screen: Screen,
+}
+
+impl ScreenModel {
+    pub fn new(cols: u16, rows: u16) -> Self {
+        ScreenModel {
+            parser: Parser::new(),
+            screen: Screen::new(cols, rows),
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) {
+        self.parser.advance(&mut self.screen, data);
+    }
+
+    /// Lines `start..end` (indices into scrollback + screen), plain or with SGR styling.
+    pub fn text(&self, start: usize, end: usize, styled: bool) -> Vec<String> {
+        let end = end.min(self.screen.total_lines());
+        (start.min(end)..end)
+            .filter_map(|i| self.screen.line(i))
+            .map(|row| {
+                if styled {
+                    row.styled_text()
+                } else {
+                    row.text()
+                }
+            })
+            .collect()
+    }
+}
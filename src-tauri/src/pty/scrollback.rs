@@ -0,0 +1,329 @@
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use super::screen::Row;
+use super::types::{MatchRange, SearchMatch, SearchResults};
+use crate::settings::{self, terminal_plus_dir};
+
+/// Rows compressed together; the archive grows and shrinks a chunk at a time.
+const CHUNK_ROWS: usize = 1000;
+
+/// Lines of context returned on each side of a match.
+const CONTEXT_LINES: usize = 2;
+
+/// Matches returned when `pty_search` is called without a limit.
+pub const DEFAULT_SEARCH_LIMIT: usize = 200;
+
+/// Where rows that fall off the screen model's scrollback are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrollbackStorage {
+    /// Deflate-compressed chunks in memory.
+    #[default]
+    Memory,
+    /// Compressed chunks under `~/.terminal-plus/scrollback/`, removed with the session.
+    Disk,
+}
+
+/// The `scrollback` section of `~/.terminal-plus/settings.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScrollbackConfig {
+    pub storage: ScrollbackStorage,
+    /// Compressed size kept per session; the oldest output goes first.
+    pub max_mb: u64,
+}
+
+impl Default for ScrollbackConfig {
+    fn default() -> Self {
+        ScrollbackConfig {
+            storage: ScrollbackStorage::Memory,
+            max_mb: 32,
+        }
+    }
+}
+
+impl ScrollbackConfig {
+    /// Load the config, using the defaults when the settings file doesn't exist.
+    pub fn load() -> Result<Self, String> {
+        settings::load_section("scrollback")
+    }
+}
+
+/// Append `row` to `out` in the archive's text form: the row's text, then
+/// `\r` if the line continues on the next row or `\n` if it ends there.
+pub fn encode_row(out: &mut String, row: &Row) {
+    out.push_str(&row.joinable_text());
+    out.push(if row.wrapped { '\r' } else { '\n' });
+}
+
+#[derive(Clone)]
+enum ChunkData {
+    Memory(Arc<[u8]>),
+    Disk(PathBuf),
+}
+
+#[derive(Clone)]
+struct Chunk {
+    /// Row number of the chunk's first row.
+    first_row: u64,
+    /// Compressed size.
+    size: u64,
+    data: ChunkData,
+}
+
+impl Chunk {
+    /// The encoded rows, or `None` if the chunk has been deleted meanwhile.
+    fn rows(&self) -> Option<String> {
+        let compressed = match &self.data {
+            ChunkData::Memory(data) => data.to_vec(),
+            ChunkData::Disk(path) => std::fs::read(path).ok()?,
+        };
+        let mut rows = String::new();
+        DeflateDecoder::new(&compressed[..])
+            .read_to_string(&mut rows)
+            .ok()?;
+        Some(rows)
+    }
+}
+
+/// Everything a session printed that no longer fits in the screen model's
+/// scrollback, compressed and capped at [`ScrollbackConfig::max_mb`].
+pub struct ScrollbackArchive {
+    storage: ScrollbackStorage,
+    max_bytes: u64,
+    dir: PathBuf,
+    chunks: VecDeque<Chunk>,
+    /// Compressed size of `chunks`.
+    size: u64,
+    /// Encoded rows not compressed yet.
+    pending: String,
+    pending_rows: usize,
+    /// Row number of the first pending row.
+    pending_start: u64,
+}
+
+impl ScrollbackArchive {
+    pub fn new(session_id: &str, config: &ScrollbackConfig) -> Self {
+        ScrollbackArchive {
+            storage: config.storage,
+            max_bytes: config.max_mb * 1024 * 1024,
+            // Unique per archive: a restarted session gets a new one before the old is dropped
+            dir: terminal_plus_dir()
+                .join("scrollback")
+                .join(format!("{session_id}-{}", uuid::Uuid::new_v4())),
+            chunks: VecDeque::new(),
+            size: 0,
+            pending: String::new(),
+            pending_rows: 0,
+            pending_start: 0,
+        }
+    }
+
+    /// Archive rows evicted from the screen model, oldest first.
+    pub fn push(&mut self, rows: Vec<Row>) {
+        for row in &rows {
+            encode_row(&mut self.pending, row);
+        }
+        self.pending_rows += rows.len();
+        if self.pending_rows >= CHUNK_ROWS {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        let compressed = match encoder
+            .write_all(self.pending.as_bytes())
+            .and_then(|_| encoder.finish())
+        {
+            Ok(compressed) => compressed,
+            Err(_) => return,
+        };
+        let data = match self.storage {
+            ScrollbackStorage::Memory => ChunkData::Memory(compressed.as_slice().into()),
+            ScrollbackStorage::Disk => {
+                let path = self.dir.join(format!("{}.z", self.pending_start));
+                let written = std::fs::create_dir_all(&self.dir)
+                    .and_then(|_| std::fs::write(&path, &compressed));
+                // Keep the chunk in memory rather than lose it
+                match written {
+                    Ok(()) => ChunkData::Disk(path),
+                    Err(_) => ChunkData::Memory(compressed.as_slice().into()),
+                }
+            }
+        };
+        self.chunks.push_back(Chunk {
+            first_row: self.pending_start,
+            size: compressed.len() as u64,
+            data,
+        });
+        self.size += compressed.len() as u64;
+        self.pending_start += self.pending_rows as u64;
+        self.pending.clear();
+        self.pending_rows = 0;
+
+        while self.size > self.max_bytes {
+            let Some(chunk) = self.chunks.pop_front() else {
+                break;
+            };
+            self.size -= chunk.size;
+            if let ChunkData::Disk(path) = &chunk.data {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+
+    /// A copy of the archive's current contents that can be searched without
+    /// holding the session's lock.
+    pub fn snapshot(&self) -> ArchiveSnapshot {
+        ArchiveSnapshot {
+            chunks: self.chunks.iter().cloned().collect(),
+            pending: self.pending.clone(),
+            pending_start: self.pending_start,
+        }
+    }
+}
+
+impl Drop for ScrollbackArchive {
+    fn drop(&mut self) {
+        if self.storage == ScrollbackStorage::Disk {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+pub struct ArchiveSnapshot {
+    chunks: Vec<Chunk>,
+    pending: String,
+    pending_start: u64,
+}
+
+impl ArchiveSnapshot {
+    /// Search the archive, followed by `live` (the encoded rows of the screen
+    /// model, starting at row `live_start`). Returns the newest `limit` matches.
+    pub fn search(
+        &self,
+        live: &str,
+        live_start: u64,
+        regex: &Regex,
+        limit: usize,
+    ) -> SearchResults {
+        let first_line = self
+            .chunks
+            .first()
+            .map_or(self.pending_start, |c| c.first_row);
+        let mut searcher = Searcher {
+            regex,
+            limit,
+            next_row: first_line,
+            line: String::new(),
+            line_start: None,
+            before: VecDeque::new(),
+            matches: VecDeque::new(),
+            total: 0,
+        };
+        for chunk in &self.chunks {
+            if let Some(rows) = chunk.rows() {
+                searcher.feed(chunk.first_row, &rows);
+            }
+        }
+        searcher.feed(self.pending_start, &self.pending);
+        searcher.feed(live_start, live);
+        searcher.end_line();
+        SearchResults {
+            matches: searcher.matches.into(),
+            total_matches: searcher.total,
+            first_line,
+            screen_first_line: live_start,
+        }
+    }
+}
+
+/// Joins encoded rows back into lines and matches them as they complete.
+struct Searcher<'a> {
+    regex: &'a Regex,
+    limit: usize,
+    /// Row number of the next row fed.
+    next_row: u64,
+    line: String,
+    /// First row of `line`, while one is being assembled.
+    line_start: Option<u64>,
+    /// The last lines seen, for the context of the next match.
+    before: VecDeque<String>,
+    matches: VecDeque<SearchMatch>,
+    total: usize,
+}
+
+impl Searcher<'_> {
+    fn feed(&mut self, first_row: u64, rows: &str) {
+        if first_row != self.next_row {
+            // A chunk went missing: don't join lines or share context across the gap
+            self.end_line();
+            self.before.clear();
+            self.next_row = first_row;
+        }
+        let mut rest = rows;
+        while let Some(end) = rest.find(['\n', '\r']) {
+            self.line_start.get_or_insert(self.next_row);
+            self.line.push_str(&rest[..end]);
+            self.next_row += 1;
+            if rest.as_bytes()[end] == b'\n' {
+                self.end_line();
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    fn end_line(&mut self) {
+        let Some(line) = self.line_start.take() else {
+            return;
+        };
+        let text = std::mem::take(&mut self.line);
+
+        // Only the last few matches can still be waiting for context
+        for m in self.matches.iter_mut().rev().take(CONTEXT_LINES) {
+            if m.context_after.len() < CONTEXT_LINES {
+                m.context_after.push(text.clone());
+            }
+        }
+
+        let ranges: Vec<MatchRange> = self
+            .regex
+            .find_iter(&text)
+            .filter(|m| !m.is_empty())
+            .map(|m| {
+                let start = text[..m.start()].chars().count();
+                MatchRange {
+                    start,
+                    end: start + m.as_str().chars().count(),
+                }
+            })
+            .collect();
+        if !ranges.is_empty() {
+            self.total += 1;
+            self.matches.push_back(SearchMatch {
+                line,
+                text: text.clone(),
+                ranges,
+                context_before: self.before.iter().cloned().collect(),
+                context_after: Vec::new(),
+            });
+            if self.matches.len() > self.limit {
+                self.matches.pop_front();
+            }
+        }
+
+        self.before.push_back(text);
+        if self.before.len() > CONTEXT_LINES {
+            self.before.pop_front();
+        }
+    }
+}
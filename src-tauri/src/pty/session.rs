@@ -1,16 +1,349 @@
-use base64::Engine;
-use parking_lot::Mutex;
+use parking_lot::{Condvar, Mutex};
+#[cfg(not(unix))]
+use portable_pty::ChildKiller;
 use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::io::Read;
-use tauri::{AppHandle, Emitter};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::backend::SessionBackend;
+use super::blocks::{self, BlockTracker};
+use super::history::{OutputHistory, HISTORY_CAPACITY};
+use super::manager::PtyManager;
+use super::monitor::OutputMonitor;
+use super::notify::{self, RateLimiter};
+use super::osc::{self, OscScanner};
+use super::output::{FlowControl, OutputStream, OutputSwitch};
+use super::palette::Shell;
+use super::process;
+use super::profile::{IntegrationLevel, ShellProfile};
+use super::recording::Recorder;
+use super::screen::ScreenModel;
+use super::scrollback::{self, ScrollbackArchive, ScrollbackConfig};
+use super::types::{
+    BlockOutput, CommandBlock, ForegroundProcess, LineRange, NotificationKind, PtyExit,
+    PtyNotification, PtyTeardown, RecordingInfo, ScreenText, SearchResults, SessionInfo,
+    TeardownOutcome,
+};
+#[cfg(unix)]
+use crate::daemon::client::{DaemonClient, DaemonStream};
+#[cfg(unix)]
+use crate::daemon::protocol::{SessionEntry, SpawnRequest};
+use crate::tmux::ControlMode;
+
+/// Grace period between SIGHUP and SIGKILL when `pty_destroy` doesn't specify one.
+pub const DEFAULT_GRACE_MS: u64 = 2000;
+
+/// How long to wait for the kernel to deliver SIGKILL before giving up on the exit status.
+const KILL_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How often the foreground watcher checks the PTY's foreground process group.
+const FOREGROUND_POLL: Duration = Duration::from_millis(500);
+
+/// Pause before restarting a program, so one that fails immediately doesn't spin.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Most output replayed to a frontend that reattaches. More than the flow
+/// control lets a detached session run ahead, so nothing it printed is lost.
+const ATTACH_BACKLOG: usize = 1024 * 1024;
+
+/// What happens once the session's program exits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitPolicy {
+    /// The frontend closes the pane.
+    Close,
+    /// The pane stays open with an exit message.
+    #[default]
+    Hold,
+    /// The program is started again in the same session.
+    Restart,
+}
+
+/// The input side of a session's PTY, shared with tmux control mode.
+pub type PtyWriter = Arc<Mutex<Box<dyn std::io::Write + Send>>>;
+
+/// Parameters for starting a session.
+#[derive(Clone)]
+pub struct SpawnOptions {
+    pub cols: u16,
+    pub rows: u16,
+    /// Explicit starting directory; wins over the profile's.
+    pub cwd: Option<String>,
+    pub profile: ShellProfile,
+    pub scrollback: ScrollbackConfig,
+    pub on_exit: ExitPolicy,
+}
+
+/// What a restarted program keeps from its previous run in the session.
+pub struct CarriedSettings {
+    pub notifications_muted: bool,
+    /// Activity and silence thresholds.
+    pub monitor: (Option<Duration>, Option<Duration>),
+}
+
+/// The master side of the session's PTY, held here or by the session daemon.
+enum Master {
+    Local(Box<dyn MasterPty + Send>),
+    #[cfg(unix)]
+    Daemon {
+        client: Arc<DaemonClient>,
+        session_id: String,
+        size: PtySize,
+    },
+}
+
+impl Master {
+    fn resize(&mut self, size: PtySize) -> Result<(), String> {
+        match self {
+            Master::Local(master) => master
+                .resize(size)
+                .map_err(|e| format!("PTY resize failed: {e}")),
+            #[cfg(unix)]
+            Master::Daemon {
+                client,
+                session_id,
+                size: current,
+            } => {
+                client.resize(session_id, size.cols, size.rows)?;
+                *current = size;
+                Ok(())
+            }
+        }
+    }
+
+    fn get_size(&self) -> Result<PtySize, String> {
+        match self {
+            Master::Local(master) => master
+                .get_size()
+                .map_err(|e| format!("Failed to get PTY size: {e}")),
+            #[cfg(unix)]
+            Master::Daemon { size, .. } => Ok(*size),
+        }
+    }
+
+    #[cfg(unix)]
+    fn process_group_leader(&self) -> Option<libc::pid_t> {
+        match self {
+            Master::Local(master) => master.process_group_leader(),
+            Master::Daemon {
+                client, session_id, ..
+            } => client.foreground_pgrp(session_id),
+        }
+    }
+
+    /// The session is gone for good: let the daemon forget it too.
+    fn release(&self) {
+        #[cfg(unix)]
+        if let Master::Daemon {
+            client, session_id, ..
+        } = self
+        {
+            client.close(session_id);
+        }
+    }
+}
+
+/// The session's program, for the waiter thread.
+enum Program {
+    Local(Box<dyn Child + Send + Sync>),
+    #[cfg(unix)]
+    Daemon {
+        client: Arc<DaemonClient>,
+        session_id: String,
+    },
+}
+
+impl Program {
+    /// Block until the program exits, returning `(exit_code, signal)`.
+    fn wait(&mut self) -> (Option<i32>, Option<i32>) {
+        match self {
+            Program::Local(child) => wait_for_exit(child),
+            #[cfg(unix)]
+            Program::Daemon { client, session_id } => client.wait(session_id),
+        }
+    }
+}
+
+/// A program started on a PTY, ready to be wired up as a session.
+struct Launched {
+    master: Master,
+    program: Program,
+    reader: Box<dyn Read + Send>,
+    writer: Box<dyn std::io::Write + Send>,
+    pid: Option<u32>,
+    /// Bytes at the start of `reader` that an earlier run of the app already
+    /// showed: the daemon's backlog when taking a session over.
+    backlog: u64,
+}
+
+/// Everything else a session needs to know about its program.
+struct Setup {
+    spawned_at: Instant,
+    /// Milliseconds since the Unix epoch.
+    started_at: u64,
+    cwd: String,
+    shell: String,
+    profile_id: String,
+    cols: u16,
+    rows: u16,
+    scrollback: ScrollbackConfig,
+    /// Everything needed to start the program again once it exits.
+    restart: Option<SpawnOptions>,
+}
+
+#[cfg(unix)]
+impl Launched {
+    fn daemon(
+        client: Arc<DaemonClient>,
+        session_id: &str,
+        stream: DaemonStream,
+        size: PtySize,
+    ) -> Self {
+        Launched {
+            master: Master::Daemon {
+                client: client.clone(),
+                session_id: session_id.to_string(),
+                size,
+            },
+            program: Program::Daemon {
+                client,
+                session_id: session_id.to_string(),
+            },
+            reader: Box::new(stream.reader),
+            writer: Box::new(stream.writer),
+            pid: stream.pid,
+            backlog: stream.backlog,
+        }
+    }
+}
+
+/// What the app needs to take a session back over from the daemon after a
+/// restart. The daemon stores it without looking inside.
+#[cfg(unix)]
+#[derive(Serialize, Deserialize)]
+struct DaemonMeta {
+    profile: ShellProfile,
+    on_exit: ExitPolicy,
+    cwd: String,
+    started_at: u64,
+}
 
 pub struct PtySession {
-    master: Mutex<Box<dyn MasterPty + Send>>,
-    writer: Mutex<Box<dyn std::io::Write + Send>>,
-    child: Mutex<Box<dyn Child + Send + Sync>>,
+    session_id: String,
+    profile_id: String,
+    /// Milliseconds since the Unix epoch.
+    started_at: u64,
+    master: Arc<Mutex<Master>>,
+    writer: PtyWriter,
+    #[cfg(not(unix))]
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+    pid: Option<u32>,
+    exit: Arc<ExitState>,
+    prompt: Arc<PromptState>,
+    flow: Arc<FlowControl>,
+    output: OutputSwitch,
+    transcript: Arc<Mutex<Transcript>>,
+    recorder: Arc<Recorder>,
+    /// Drop bells and OSC 9/777 notifications instead of announcing them.
+    notifications_muted: Arc<AtomicBool>,
+    monitor: Arc<OutputMonitor>,
     pub cwd: String,
 }
 
+/// What the reader thread learns from the output stream.
+struct Transcript {
+    history: OutputHistory,
+    blocks: BlockTracker,
+    screen: ScreenModel,
+    /// Rows that fell off the screen model's scrollback.
+    scrollback: ScrollbackArchive,
+    /// Directory the shell last reported via OSC 7.
+    cwd: Option<String>,
+    /// Whether `cwd` still holds. Shells report it before every prompt, so it
+    /// goes stale once a command starts: that command may `cd`, `exec` a
+    /// shell without integration or open a subshell.
+    cwd_current: bool,
+}
+
+impl Transcript {
+    fn archive_evicted(&mut self) {
+        let evicted = self.screen.screen.take_evicted();
+        if !evicted.is_empty() {
+            self.scrollback.push(evicted);
+        }
+    }
+}
+
+/// Exit status of the child, filled in by the waiter thread once it has been reaped.
+#[derive(Default)]
+struct ExitState {
+    status: Mutex<Option<PtyExit>>,
+    cond: Condvar,
+}
+
+impl ExitState {
+    fn set(&self, exit: PtyExit) {
+        *self.status.lock() = Some(exit);
+        self.cond.notify_all();
+    }
+
+    fn get(&self) -> Option<PtyExit> {
+        self.status.lock().clone()
+    }
+
+    fn wait(&self) -> PtyExit {
+        let mut status = self.status.lock();
+        loop {
+            if let Some(exit) = status.as_ref() {
+                return exit.clone();
+            }
+            self.cond.wait(&mut status);
+        }
+    }
+
+    fn wait_timeout(&self, timeout: Duration) -> Option<PtyExit> {
+        let deadline = Instant::now() + timeout;
+        let mut status = self.status.lock();
+        while status.is_none() {
+            if self.cond.wait_until(&mut status, deadline).timed_out() {
+                break;
+            }
+        }
+        status.clone()
+    }
+}
+
+/// Whether the shell has drawn a prompt (OSC 133;A) yet, so input typed from
+/// the backend isn't read by its rc files instead.
+#[derive(Default)]
+struct PromptState {
+    seen: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl PromptState {
+    fn set(&self) {
+        let mut seen = self.seen.lock();
+        if !*seen {
+            *seen = true;
+            self.cond.notify_all();
+        }
+    }
+
+    fn wait_timeout(&self, timeout: Duration) -> bool {
+        let mut seen = self.seen.lock();
+        self.cond.wait_while_for(&mut seen, |seen| !*seen, timeout);
+        *seen
+    }
+}
+
 /// Set up shell integration files for zsh.
 /// Creates a ZDOTDIR that proxies the user's config and appends our prompt/colors.
 fn setup_zsh_integration(home: &str) -> Result<String, String> {
@@ -40,6 +373,8 @@ fn setup_zsh_integration(home: &str) -> Result<String, String> {
     )
     .map_err(|e| format!("Failed to write .zprofile: {e}"))?;
 
+    Shell::Zsh.ensure_theme(home)?;
+
     // .zshrc — source user config first, then apply our integration
     std::fs::write(
         format!("{integration_dir}/.zshrc"),
@@ -63,6 +398,7 @@ fn setup_bash_integration(home: &str) -> Result<String, String> {
     std::fs::create_dir_all(&integration_dir)
         .map_err(|e| format!("Failed to create shell integration dir: {e}"))?;
 
+    Shell::Bash.ensure_theme(home)?;
     std::fs::write(
         format!("{integration_dir}/.bashrc"),
         include_str!("shell_integration.bash"),
@@ -72,15 +408,64 @@ fn setup_bash_integration(home: &str) -> Result<String, String> {
     Ok(format!("{integration_dir}/.bashrc"))
 }
 
+/// Set up shell integration files for fish.
+/// Sourced with `--init-command` rather than through XDG_CONFIG_HOME, which
+/// every program started from the shell would inherit.
+fn setup_fish_integration(home: &str) -> Result<String, String> {
+    let integration_dir = format!("{home}/.terminal-plus/shell/fish");
+    std::fs::create_dir_all(&integration_dir)
+        .map_err(|e| format!("Failed to create shell integration dir: {e}"))?;
+
+    Shell::Fish.ensure_theme(home)?;
+    std::fs::write(
+        format!("{integration_dir}/config.fish"),
+        include_str!("shell_integration.fish"),
+    )
+    .map_err(|e| format!("Failed to write config.fish: {e}"))?;
+
+    Ok(format!("{integration_dir}/config.fish"))
+}
+
+/// Set up shell integration files for nushell, sourced with `--execute`.
+fn setup_nu_integration(home: &str) -> Result<String, String> {
+    let integration_dir = format!("{home}/.terminal-plus/shell/nu");
+    std::fs::create_dir_all(&integration_dir)
+        .map_err(|e| format!("Failed to create shell integration dir: {e}"))?;
+
+    Shell::Nu.ensure_theme(home)?;
+    std::fs::write(
+        format!("{integration_dir}/config.nu"),
+        include_str!("shell_integration.nu"),
+    )
+    .map_err(|e| format!("Failed to write config.nu: {e}"))?;
+
+    Ok(format!("{integration_dir}/config.nu"))
+}
+
+/// Whether `shell` is nushell's `nu`; a substring check like the other shells'
+/// would also match e.g. `/usr/bin/gnu-foo`.
+fn is_nushell(shell: &str) -> bool {
+    Path::new(shell)
+        .file_stem()
+        .is_some_and(|stem| stem == "nu")
+}
+
 impl PtySession {
     pub fn spawn(
         app: AppHandle,
         session_id: String,
-        cols: u16,
-        rows: u16,
-        cwd: Option<String>,
+        options: SpawnOptions,
+        on_output: Channel,
     ) -> Result<Self, String> {
-        let pty_system = native_pty_system();
+        let restart = (options.on_exit == ExitPolicy::Restart).then(|| options.clone());
+        let SpawnOptions {
+            cols,
+            rows,
+            cwd,
+            profile,
+            scrollback,
+            on_exit,
+        } = options;
 
         let size = PtySize {
             rows,
@@ -89,17 +474,20 @@ impl PtySession {
             pixel_height: 0,
         };
 
-        let pair = pty_system
-            .openpty(size)
-            .map_err(|e| format!("Failed to open PTY: {e}"))?;
-
-        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+        let shell = profile.program();
+        let profile_id = profile.id.clone();
+        let started_at = unix_millis();
         let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
 
         let mut cmd = CommandBuilder::new(&shell);
-        cmd.arg("-l");
+        if profile.login() {
+            cmd.arg("-l");
+        }
 
-        let working_dir = cwd.unwrap_or_else(|| home.clone());
+        // An explicit cwd (e.g. inherited from the focused pane) wins over the profile's
+        let working_dir = cwd
+            .or_else(|| profile.cwd(&home))
+            .unwrap_or_else(|| home.clone());
         cmd.cwd(&working_dir);
 
         // Inherit common env vars
@@ -113,66 +501,472 @@ impl PtySession {
         cmd.env("CLICOLOR", "1");
         cmd.env("CLICOLOR_FORCE", "1");
 
-        // Shell integration
-        if shell.contains("zsh") {
+        // Shell integration, unless the profile turns it off (command panes always do)
+        let shell_integration = profile.integration != IntegrationLevel::Off;
+        if shell_integration {
+            cmd.env("TERMINAL_PLUS_INTEGRATION", profile.integration.as_str());
+        }
+        if shell_integration && shell.contains("zsh") {
             if let Ok(zdotdir) = setup_zsh_integration(&home) {
                 if let Ok(orig) = std::env::var("ZDOTDIR") {
                     cmd.env("TERMINAL_PLUS_ORIG_ZDOTDIR", orig);
                 }
                 cmd.env("ZDOTDIR", zdotdir);
             }
-        } else if shell.contains("bash") {
+        } else if shell_integration && shell.contains("bash") {
             if let Ok(rcfile) = setup_bash_integration(&home) {
                 cmd.env("TERMINAL_PLUS_BASH_RCFILE", &rcfile);
                 // For login bash, .bash_profile is read, not .bashrc.
                 // We set BASH_ENV so non-interactive sub-shells also pick it up.
                 cmd.env("BASH_ENV", &rcfile);
+                // A non-login interactive bash reads ~/.bashrc, so point it at ours instead.
+                if !profile.login() {
+                    cmd.arg("--rcfile");
+                    cmd.arg(&rcfile);
+                }
             }
+        } else if shell_integration && shell.contains("fish") {
+            // Runs after config.fish, like our .zshrc/.bashrc after the user's
+            if let Ok(config) = setup_fish_integration(&home) {
+                cmd.arg("--init-command");
+                cmd.arg(format!("source '{config}'"));
+            }
+        } else if shell_integration && is_nushell(&shell) {
+            // Runs after env.nu and config.nu, before the first prompt
+            if let Ok(config) = setup_nu_integration(&home) {
+                cmd.arg("--execute");
+                cmd.arg(format!("source '{config}'"));
+            }
+        }
+
+        // Profile args and env come last so they can override the defaults above
+        cmd.args(&profile.args);
+        for (key, val) in &profile.env {
+            cmd.env(key, val);
+        }
+
+        // With the session daemon on, the program runs there and outlives the app
+        #[cfg(unix)]
+        if let Some(client) = app.state::<PtyManager>().daemon() {
+            let meta = DaemonMeta {
+                profile,
+                on_exit,
+                cwd: working_dir.clone(),
+                started_at,
+            };
+            let request = SpawnRequest {
+                session_id: session_id.clone(),
+                argv: cmd
+                    .get_argv()
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().into_owned())
+                    .collect(),
+                env: cmd
+                    .iter_extra_env_as_str()
+                    .map(|(key, val)| (key.to_string(), val.to_string()))
+                    .collect(),
+                cwd: Some(working_dir.clone()),
+                cols,
+                rows,
+                meta: serde_json::to_value(&meta)
+                    .map_err(|e| format!("Failed to encode session: {e}"))?,
+            };
+            let stream = client.spawn(request)?;
+            let launched = Launched::daemon(client, &session_id, stream, size);
+            let setup = Setup {
+                spawned_at: Instant::now(),
+                started_at,
+                cwd: working_dir,
+                shell,
+                profile_id,
+                cols,
+                rows,
+                scrollback,
+                restart,
+            };
+            return Ok(Self::start(app, session_id, launched, setup, on_output));
         }
+        #[cfg(not(unix))]
+        let _ = on_exit;
 
+        let pair = native_pty_system()
+            .openpty(size)
+            .map_err(|e| format!("Failed to open PTY: {e}"))?;
         let child = pair
             .slave
             .spawn_command(cmd)
             .map_err(|e| format!("Failed to spawn shell: {e}"))?;
+        let spawned_at = Instant::now();
+        let pid = child.process_id();
 
         let writer = pair
             .master
             .take_writer()
             .map_err(|e| format!("Failed to get PTY writer: {e}"))?;
 
-        let mut reader = pair
+        let reader = pair
             .master
             .try_clone_reader()
             .map_err(|e| format!("Failed to get PTY reader: {e}"))?;
 
+        let launched = Launched {
+            master: Master::Local(pair.master),
+            program: Program::Local(child),
+            reader,
+            writer,
+            pid,
+            backlog: 0,
+        };
+        let setup = Setup {
+            spawned_at,
+            started_at,
+            cwd: working_dir,
+            shell,
+            profile_id,
+            cols,
+            rows,
+            scrollback,
+            restart,
+        };
+        Ok(Self::start(app, session_id, launched, setup, on_output))
+    }
+
+    /// Take over a session the daemon kept running from a previous run of the
+    /// app. Its kept output is replayed into history, screen, blocks and cwd
+    /// before anything is shown, without notifying or recording it again.
+    #[cfg(unix)]
+    pub fn adopt(
+        app: AppHandle,
+        client: Arc<DaemonClient>,
+        entry: SessionEntry,
+        on_output: Channel,
+    ) -> Result<Self, String> {
+        let meta: DaemonMeta = serde_json::from_value(entry.meta)
+            .map_err(|e| format!("Unreadable daemon session {}: {e}", entry.session_id))?;
+        let scrollback = ScrollbackConfig::load()?;
+        let restart = (meta.on_exit == ExitPolicy::Restart).then(|| SpawnOptions {
+            cols: entry.cols,
+            rows: entry.rows,
+            cwd: Some(meta.cwd.clone()),
+            profile: meta.profile.clone(),
+            scrollback: scrollback.clone(),
+            on_exit: meta.on_exit,
+        });
+        let size = PtySize {
+            rows: entry.rows,
+            cols: entry.cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        let stream = client.attach(&entry.session_id)?;
+        let launched = Launched::daemon(client, &entry.session_id, stream, size);
+        let runtime = Duration::from_millis(unix_millis().saturating_sub(meta.started_at));
+        let setup = Setup {
+            spawned_at: Instant::now()
+                .checked_sub(runtime)
+                .unwrap_or_else(Instant::now),
+            started_at: meta.started_at,
+            cwd: meta.cwd,
+            shell: meta.profile.program(),
+            profile_id: meta.profile.id,
+            cols: entry.cols,
+            rows: entry.rows,
+            scrollback,
+            restart,
+        };
+        Ok(Self::start(
+            app,
+            entry.session_id,
+            launched,
+            setup,
+            on_output,
+        ))
+    }
+
+    /// Wire a launched program up as a session: waiter, reader and watcher threads.
+    fn start(
+        app: AppHandle,
+        session_id: String,
+        launched: Launched,
+        setup: Setup,
+        on_output: Channel,
+    ) -> Self {
+        let Launched {
+            master,
+            mut program,
+            mut reader,
+            writer,
+            pid,
+            backlog,
+        } = launched;
+        let Setup {
+            spawned_at,
+            started_at,
+            cwd: working_dir,
+            shell,
+            profile_id,
+            cols,
+            rows,
+            scrollback,
+            restart,
+        } = setup;
+        #[cfg(not(unix))]
+        let killer = match &program {
+            Program::Local(child) => child.clone_killer(),
+        };
+        let exit = Arc::new(ExitState::default());
+
+        // Waiter thread — reaps the child as soon as it exits, independent of EOF
+        // (a backgrounded job can keep the slave open after the shell is gone)
+        let reaped_exit = exit.clone();
+        std::thread::spawn(move || {
+            let (exit_code, signal) = program.wait();
+            reaped_exit.set(PtyExit {
+                exit_code,
+                signal,
+                signal_name: signal.map(signal_name),
+                runtime_ms: spawned_at.elapsed().as_millis() as u64,
+                error: None,
+            });
+        });
+
+        let master = Arc::new(Mutex::new(master));
+        spawn_foreground_watcher(
+            app.clone(),
+            session_id.clone(),
+            Arc::downgrade(&master),
+            pid,
+            exit.clone(),
+        );
+
+        let writer: PtyWriter = Arc::new(Mutex::new(writer));
+        let mut control = ControlMode::new(app.clone(), session_id.clone(), writer.clone());
+
         let sid = session_id.clone();
+        let reader_exit = exit.clone();
+        let flow = Arc::new(FlowControl::default());
+        let output = OutputStream::new(on_output, flow.clone());
+        let output_switch = output.switch();
+        let transcript = Arc::new(Mutex::new(Transcript {
+            history: OutputHistory::new(HISTORY_CAPACITY),
+            blocks: BlockTracker::default(),
+            screen: ScreenModel::new(cols, rows),
+            scrollback: ScrollbackArchive::new(&session_id, &scrollback),
+            cwd: None,
+            cwd_current: false,
+        }));
+        let reader_transcript = transcript.clone();
+        let recorder = Arc::new(Recorder::new(shell));
+        let reader_recorder = recorder.clone();
+        let notifications_muted = Arc::new(AtomicBool::new(false));
+        let reader_muted = notifications_muted.clone();
+        let monitor = OutputMonitor::spawn(app.clone(), &session_id);
+        let reader_monitor = monitor.clone();
+        let prompt = Arc::new(PromptState::default());
+        let reader_prompt = prompt.clone();
 
         // Dedicated reader thread — blocking I/O
         std::thread::spawn(move || {
             let mut buf = [0u8; 8192];
-            let event_name = format!("pty-output-{sid}");
+            let mut scanner = OscScanner::default();
+            let cwd_event = format!("pty-cwd-changed-{sid}");
+            let notify_event = format!("pty-notify-{sid}");
+            let mut rate_limiter = RateLimiter::default();
+            let mut replay = backlog;
             loop {
+                if output.over_capacity() {
+                    reader_monitor.set_paused(true);
+                    output.wait_for_capacity();
+                    reader_monitor.set_paused(false);
+                }
                 match reader.read(&mut buf) {
                     Ok(0) => break,
                     Ok(n) => {
-                        let encoded = base64::engine::general_purpose::STANDARD.encode(&buf[..n]);
-                        let _ = app.emit(&event_name, encoded);
+                        // The daemon's backlog rebuilds history, screen, blocks and
+                        // cwd, but was announced by the previous run already. Reads
+                        // don't straddle it, as it comes in a frame of its own.
+                        let replayed = replay > 0;
+                        replay = replay.saturating_sub(n as u64);
+                        // Output in tmux control mode belongs to the tmux panes
+                        let data = control.feed(&buf[..n]);
+                        if data.is_empty() {
+                            continue;
+                        }
+                        if !replayed {
+                            reader_recorder.output(&data);
+                            reader_monitor.output();
+                        }
+                        let mut new_cwd = None;
+                        let mut notifications = Vec::new();
+                        let mut transcript = reader_transcript.lock();
+                        // Together under the lock, so a reattach sees every byte in
+                        // either its backlog or the stream, never both
+                        transcript.history.push(&data);
+                        if !replayed {
+                            output.push(&data);
+                        }
+                        transcript.screen.feed(&data);
+                        transcript.archive_evicted();
+                        if transcript.screen.screen.take_bell() {
+                            notifications.push(PtyNotification {
+                                kind: NotificationKind::Bell,
+                                title: None,
+                                body: None,
+                            });
+                        }
+                        for seq in scanner.feed(&data) {
+                            match seq.code {
+                                7 => {
+                                    // The shell reports on every prompt; only announce actual changes
+                                    let cwd = osc::parse_cwd_url(&seq.data);
+                                    transcript.cwd_current |= cwd.is_some();
+                                    if cwd.is_some() && cwd != transcript.cwd {
+                                        transcript.cwd = cwd.clone();
+                                        new_cwd = cwd;
+                                    }
+                                }
+                                133 => {
+                                    if seq.data.starts_with('A') {
+                                        reader_prompt.set();
+                                    }
+                                    if seq.data.starts_with('C') {
+                                        transcript.cwd_current = false;
+                                    }
+                                    let Transcript { blocks, cwd, .. } = &mut *transcript;
+                                    blocks.apply(&seq, cwd.as_deref());
+                                }
+                                9 | 777 => notifications.extend(notify::from_osc(&seq)),
+                                _ => {}
+                            }
+                        }
+                        // Only needed to say where a bell came from
+                        let cwd = if notifications.is_empty() {
+                            None
+                        } else {
+                            transcript.cwd.clone()
+                        };
+                        drop(transcript);
+                        if replayed {
+                            continue;
+                        }
+                        if let Some(cwd) = new_cwd {
+                            let _ = app.emit(&cwd_event, cwd);
+                        }
+                        if reader_muted.load(Ordering::Relaxed) {
+                            notifications.clear();
+                        }
+                        for notification in notifications {
+                            if rate_limiter.allow() {
+                                let _ = app.emit(&notify_event, &notification);
+                                notify::show_native(&app, &notification, cwd.as_deref());
+                            }
+                        }
                     }
                     Err(_) => break,
                 }
             }
-            let _ = app.emit(&format!("pty-exit-{sid}"), ());
+            control.close();
+            // Flush the tail of the output (and any recording) before announcing the exit
+            let on_output = output.channel();
+            drop(output);
+            reader_recorder.stop();
+            reader_monitor.close();
+            // EOF on the master — report once the waiter thread has reaped the child
+            let _ = app.emit(&format!("pty-exit-{sid}"), reader_exit.wait());
+            if let Some(options) = restart {
+                std::thread::sleep(RESTART_DELAY);
+                let carried = CarriedSettings {
+                    notifications_muted: reader_muted.load(Ordering::Relaxed),
+                    monitor: reader_monitor.thresholds(),
+                };
+                let manager = app.state::<PtyManager>();
+                manager.respawn_session(app.clone(), sid, options, on_output, carried);
+            }
         });
 
-        Ok(PtySession {
-            master: Mutex::new(pair.master),
-            writer: Mutex::new(writer),
-            child: Mutex::new(child),
+        PtySession {
+            session_id,
+            profile_id,
+            started_at,
+            master,
+            writer,
+            #[cfg(not(unix))]
+            killer: Mutex::new(killer),
+            pid,
+            exit,
+            prompt,
+            flow,
+            output: output_switch,
+            transcript,
+            recorder,
+            notifications_muted,
+            monitor,
             cwd: working_dir,
-        })
+        }
     }
 
-    pub fn write(&self, data: &[u8]) -> Result<(), String> {
+    /// SIGHUP the foreground job and the shell, wait up to `grace` for them
+    /// to exit, then escalate to SIGKILL.
+    fn stop(&self, grace: Duration) -> PtyTeardown {
+        let started = Instant::now();
+        let teardown = |outcome, exit| PtyTeardown {
+            outcome,
+            exit,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        };
+
+        // Let the reader drain to EOF even if the frontend has stopped acking
+        self.flow.close();
+
+        if let Some(exit) = self.exit.get() {
+            return teardown(TeardownOutcome::AlreadyExited, Some(exit));
+        }
+
+        self.signal(Signal::Hangup);
+        if let Some(exit) = self.exit.wait_timeout(grace) {
+            return teardown(TeardownOutcome::Hangup, Some(exit));
+        }
+
+        self.signal(Signal::Kill);
+        teardown(
+            TeardownOutcome::Killed,
+            self.exit.wait_timeout(KILL_TIMEOUT),
+        )
+    }
+
+    /// Deliver a signal to the foreground process group (e.g. a running
+    /// `cargo build` or ssh) and to the shell's own process group.
+    #[cfg(unix)]
+    fn signal(&self, signal: Signal) {
+        let sig = match signal {
+            Signal::Hangup => libc::SIGHUP,
+            Signal::Kill => libc::SIGKILL,
+        };
+        if let Some(pgrp) = self.master.lock().process_group_leader() {
+            if pgrp > 0 {
+                unsafe { libc::killpg(pgrp, sig) };
+            }
+        }
+        if let Some(pid) = self.pid {
+            // The shell is a session leader, so its pid is also its process group
+            unsafe { libc::killpg(pid as libc::pid_t, sig) };
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn signal(&self, _signal: Signal) {
+        let _ = self.killer.lock().kill();
+    }
+}
+
+impl SessionBackend for PtySession {
+    /// Stop the session, then drop it from the session daemon if it runs there.
+    fn shutdown(&self, grace: Duration) -> PtyTeardown {
+        let teardown = self.stop(grace);
+        self.master.lock().release();
+        teardown
+    }
+    fn write(&self, data: &[u8]) -> Result<(), String> {
         use std::io::Write;
         let mut writer = self.writer.lock();
         writer
@@ -183,32 +977,166 @@ impl PtySession {
             .map_err(|e| format!("PTY flush failed: {e}"))
     }
 
-    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
-        self.master
-            .lock()
-            .resize(PtySize {
-                rows,
-                cols,
-                pixel_width: 0,
-                pixel_height: 0,
-            })
-            .map_err(|e| format!("PTY resize failed: {e}"))
+    /// The frontend has rendered `bytes` more bytes of output.
+    fn ack(&self, bytes: usize) {
+        self.flow.ack(bytes);
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+        self.master.lock().resize(PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        })?;
+        let mut transcript = self.transcript.lock();
+        transcript.screen.screen.resize(cols, rows);
+        transcript.archive_evicted();
+        drop(transcript);
+        self.recorder.resize(cols, rows);
+        Ok(())
+    }
+
+    fn screen_text(&self, range: Option<LineRange>, styled: bool) -> Result<ScreenText, String> {
+        let transcript = self.transcript.lock();
+        let model = &transcript.screen;
+        let screen = &model.screen;
+        let (cols, rows) = screen.size();
+        let (cursor_row, cursor_col) = screen.cursor();
+        let range = range.unwrap_or(LineRange {
+            start: screen.scrollback_len(),
+            end: screen.total_lines(),
+        });
+        Ok(ScreenText {
+            lines: model.text(range.start, range.end, styled),
+            start: range.start.min(screen.total_lines()),
+            total_lines: screen.total_lines(),
+            scrollback_lines: screen.scrollback_len(),
+            cols,
+            rows,
+            cursor_row,
+            cursor_col,
+        })
+    }
+
+    /// Search everything the session has printed: the archive, then the
+    /// screen model's scrollback and visible screen.
+    fn search(&self, regex: &Regex, limit: usize) -> Result<SearchResults, String> {
+        let transcript = self.transcript.lock();
+        let archive = transcript.scrollback.snapshot();
+        let screen = &transcript.screen.screen;
+        let mut live = String::new();
+        for row in (0..screen.total_lines()).filter_map(|i| screen.line(i)) {
+            scrollback::encode_row(&mut live, row);
+        }
+        let live_start = screen.first_line();
+        drop(transcript);
+        Ok(archive.search(&live, live_start, regex, limit))
+    }
+
+    /// Start teeing output and resizes into an asciicast v2 file at `path`.
+    fn start_recording(&self, path: String) -> Result<RecordingInfo, String> {
+        let size = self.master.lock().get_size()?;
+        self.recorder.start(path, size.cols, size.rows)
+    }
+
+    fn stop_recording(&self) -> Option<RecordingInfo> {
+        self.recorder.stop()
+    }
+
+    fn set_notifications_muted(&self, muted: bool) -> Result<(), String> {
+        self.notifications_muted.store(muted, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_monitor(
+        &self,
+        activity_after: Option<Duration>,
+        silence_after: Option<Duration>,
+    ) -> Result<(), String> {
+        self.monitor.configure(activity_after, silence_after);
+        Ok(())
+    }
+
+    fn wait_for_prompt(&self, timeout: Duration) -> bool {
+        self.prompt.wait_timeout(timeout)
+    }
+
+    /// The process currently in the foreground of the PTY — the shell itself
+    /// when it is sitting at a prompt.
+    fn foreground_process(&self) -> Option<ForegroundProcess> {
+        let pgrp = foreground_pgrp(&self.master)?;
+        process::foreground_process(pgrp, self.pid)
+    }
+
+    /// Commands run in this session, oldest first.
+    fn blocks(&self) -> Vec<CommandBlock> {
+        self.transcript.lock().blocks.list()
+    }
+
+    /// A block's output as plain text. A running command's output so far.
+    fn block_output(&self, block_id: u64) -> Option<BlockOutput> {
+        let transcript = self.transcript.lock();
+        let block = transcript.blocks.get(block_id)?;
+        let end = block.output_end.unwrap_or_else(|| transcript.history.end());
+        let (raw, truncated) = transcript.history.range(block.output_start, end);
+        drop(transcript);
+        Some(BlockOutput {
+            block,
+            text: blocks::strip_escapes(&raw),
+            truncated,
+        })
+    }
+
+    fn info(&self) -> Option<SessionInfo> {
+        Some(SessionInfo {
+            session_id: self.session_id.clone(),
+            pid: self.pid,
+            cwd: self.get_cwd(),
+            profile_id: self.profile_id.clone(),
+            started_at: self.started_at,
+            exit: self.exit.get(),
+        })
+    }
+
+    /// Send output to `on_output` from now on, starting with the last
+    /// [`ATTACH_BACKLOG`] bytes so the frontend can rebuild its screen.
+    fn attach(&self, on_output: Channel) -> Result<(), String> {
+        let transcript = self.transcript.lock();
+        let backlog = transcript.history.tail_from_line(ATTACH_BACKLOG);
+        // Still under the lock, so the reader can't push output in between
+        self.output.attach(on_output, backlog);
+        Ok(())
     }
 
     /// Get the current working directory of the shell process.
-    /// Queries the OS for the live cwd (handles `cd` in the shell).
-    /// Falls back to the initial cwd if the OS query fails.
-    pub fn get_cwd(&self) -> String {
-        let child = self.child.lock();
-        if let Some(pid) = child.process_id() {
+    /// Prefers the directory reported by the shell integration (OSC 7) at
+    /// the current prompt; while a command runs, and for shells without it,
+    /// queries the OS for the live cwd, and falls back to the last reported
+    /// or initial cwd if that fails too.
+    fn get_cwd(&self) -> String {
+        let reported = {
+            let transcript = self.transcript.lock();
+            match &transcript.cwd {
+                Some(cwd) if transcript.cwd_current => return cwd.clone(),
+                cwd => cwd.clone(),
+            }
+        };
+        if let Some(pid) = self.pid {
             if let Some(cwd) = get_pid_cwd(pid) {
                 return cwd;
             }
         }
-        self.cwd.clone()
+        reported.unwrap_or_else(|| self.cwd.clone())
     }
 }
 
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
 /// Query the OS for the current working directory of a process by PID.
 #[cfg(target_os = "macos")]
 fn get_pid_cwd(pid: u32) -> Option<String> {
@@ -243,3 +1171,117 @@ fn get_pid_cwd(pid: u32) -> Option<String> {
 fn get_pid_cwd(_pid: u32) -> Option<String> {
     None
 }
+
+/// Poll the foreground process group and emit `pty-foreground-changed-{id}`
+/// whenever it changes. Stops once the child has exited or the session is dropped.
+fn spawn_foreground_watcher(
+    app: AppHandle,
+    session_id: String,
+    master: Weak<Mutex<Master>>,
+    shell_pid: Option<u32>,
+    exit: Arc<ExitState>,
+) {
+    std::thread::spawn(move || {
+        let event_name = format!("pty-foreground-changed-{session_id}");
+        let mut last_pgrp = None;
+        while exit.wait_timeout(FOREGROUND_POLL).is_none() {
+            let Some(master) = master.upgrade() else {
+                break;
+            };
+            let pgrp = foreground_pgrp(&master);
+            drop(master);
+            if pgrp != last_pgrp {
+                last_pgrp = pgrp;
+                let fg = pgrp.and_then(|p| process::foreground_process(p, shell_pid));
+                let _ = app.emit(&event_name, fg);
+            }
+        }
+    });
+}
+
+#[cfg(unix)]
+fn foreground_pgrp(master: &Mutex<Master>) -> Option<u32> {
+    master.lock().process_group_leader().map(|pgrp| pgrp as u32)
+}
+
+#[cfg(not(unix))]
+fn foreground_pgrp(_master: &Mutex<Master>) -> Option<u32> {
+    None
+}
+
+#[derive(Clone, Copy)]
+enum Signal {
+    Hangup,
+    Kill,
+}
+
+/// Block until the child exits, returning `(exit_code, signal)`.
+#[cfg(unix)]
+pub(crate) fn wait_for_exit(
+    child: &mut Box<dyn Child + Send + Sync>,
+) -> (Option<i32>, Option<i32>) {
+    // Reap by pid rather than through `Child::wait` so we get the raw signal
+    // number instead of portable_pty's description of it.
+    let Some(pid) = child.process_id() else {
+        return (None, None);
+    };
+    let mut status: libc::c_int = 0;
+    loop {
+        let ret = unsafe { libc::waitpid(pid as libc::pid_t, &mut status, 0) };
+        if ret == pid as libc::pid_t {
+            break;
+        }
+        if ret == -1 && std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+            // ECHILD — already reaped elsewhere
+            return (None, None);
+        }
+    }
+    if libc::WIFEXITED(status) {
+        (Some(libc::WEXITSTATUS(status)), None)
+    } else if libc::WIFSIGNALED(status) {
+        (None, Some(libc::WTERMSIG(status)))
+    } else {
+        (None, None)
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn wait_for_exit(
+    child: &mut Box<dyn Child + Send + Sync>,
+) -> (Option<i32>, Option<i32>) {
+    match child.wait() {
+        Ok(status) => (Some(status.exit_code() as i32), None),
+        Err(_) => (None, None),
+    }
+}
+
+/// Conventional name for a signal number, e.g. 9 -> "SIGKILL".
+#[cfg(unix)]
+fn signal_name(signal: i32) -> String {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        _ => return format!("SIG{signal}"),
+    };
+    name.to_string()
+}
+
+#[cfg(not(unix))]
+fn signal_name(signal: i32) -> String {
+    format!("SIG{signal}")
+}
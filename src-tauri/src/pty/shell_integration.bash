@@ -1,5 +1,7 @@
 # Terminal Plus — bash shell integration
 # Sources the user's config first, then applies our enhancements.
+# TERMINAL_PLUS_INTEGRATION=marks_only keeps the user's prompt, colors and
+# aliases and only adds the cwd / command marks.
 
 # Source user's original configs
 [[ -f "$HOME/.bash_profile" ]] && source "$HOME/.bash_profile"
@@ -7,27 +9,96 @@
 
 # === Terminal Plus enhancements ===
 
-# ── Colors for ls, grep, etc. ──
-export CLICOLOR=1
-export CLICOLOR_FORCE=1
-export LSCOLORS=GxFxCxDxBxegedabagaced
-export LS_COLORS='di=1;36:ln=1;35:so=1;32:pi=1;33:ex=1;31:bd=34;46:cd=34;43:su=30;41:sg=30;46:tw=30;42:ow=34;43'
+if [[ "$TERMINAL_PLUS_INTEGRATION" != marks_only ]]; then
 
-# Enable color in grep
-alias grep='grep --color=auto'
+  # ── Colors for ls, grep, etc. ──
+  export CLICOLOR=1
+  export CLICOLOR_FORCE=1
+  export LSCOLORS=GxFxCxDxBxegedabagaced
+  export LS_COLORS='di=1;36:ln=1;35:so=1;32:pi=1;33:ex=1;31:bd=34;46:cd=34;43:su=30;41:sg=30;46:tw=30;42:ow=34;43'
 
-# ── Git branch for prompt ──
-_tp_git_branch() {
-  local branch
-  branch=$(git symbolic-ref --short HEAD 2>/dev/null) || return
-  printf ' \033[38;2;176;64;212m(%s)\033[0m' "$branch"
+  # Enable color in grep
+  alias grep='grep --color=auto'
+
+  # ── Git branch for prompt ──
+  _tp_git_branch() {
+    local branch
+    branch=$(git symbolic-ref --short HEAD 2>/dev/null) || return
+    printf ' \033[38;2;%sm(%s)\033[0m' "$_tp_branch_color" "$branch"
+  }
+
+  # ── ls: append / to directories for Terminal+ link detection ──
+  # Use a function (overrides any alias) that injects -p to mark directories with /
+  ls() { command ls -p "$@"; }
+
+  # ── Two-line prompt, colored from the app theme ──
+  # user · path · (branch)
+  # ❯ cursor
+  # theme.bash is generated by Terminal+ and sets PS1 and the branch color. It is
+  # re-sourced before the next prompt whenever its version line changes, so
+  # running shells follow theme switches.
+  _tp_theme_file="$HOME/.terminal-plus/shell/bash/theme.bash"
+  _tp_theme_check() {
+    local version
+    [[ -r $_tp_theme_file ]] || return
+    read -r version < "$_tp_theme_file"
+    [[ $version == "_tp_theme_version=$_tp_theme_version" ]] || source "$_tp_theme_file"
+  }
+  _tp_theme_check
+  if [[ $PROMPT_COMMAND != *_tp_theme_check* ]]; then
+    PROMPT_COMMAND="_tp_theme_check${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+  fi
+
+fi
+
+# ── Report the working directory (OSC 7) so Terminal+ doesn't have to poll ──
+_tp_osc7() {
+  local url_path=${PWD//\%/%25}
+  printf '\033]7;file://%s%s\a' "$HOSTNAME" "${url_path// /%20}"
 }
 
-# ── ls: append / to directories for Terminal+ link detection ──
-# Use a function (overrides any alias) that injects -p to mark directories with /
-ls() { command ls -p "$@"; }
+# ── Command marks (OSC 133) so Terminal+ can split output into command blocks ──
+# Only for interactive shells: BASH_ENV makes scripts source this file too
+if [[ $- == *i* ]]; then
+  _tp_cmd_running=0
+  _tp_at_prompt=0
+
+  # First in PROMPT_COMMAND so $? is still the command's exit status
+  _tp_precmd() {
+    local exit_status=$?
+    if [[ $_tp_cmd_running == 1 ]]; then
+      printf '\033]133;D;%s\a' "$exit_status"
+      _tp_cmd_running=0
+    fi
+    _tp_osc7
+  }
+  # Last in PROMPT_COMMAND: prompts like starship rebuild PS1 there
+  _tp_prompt_ready() {
+    _tp_at_prompt=1
+    [[ $PS1 == *'133;A'* ]] || PS1='\[\033]133;A\a\]'"$PS1"'\[\033]133;B\a\]'
+  }
+
+  # bash has no preexec hook: the DEBUG trap fires before every simple command,
+  # so only the first one after the prompt marks the start of a command line
+  _tp_preexec() {
+    [[ $_tp_at_prompt == 1 ]] || return
+    _tp_at_prompt=0
+    # Empty command line — PROMPT_COMMAND is running again
+    [[ $BASH_COMMAND == _tp_precmd ]] && return
+    _tp_cmd_running=1
+    local cmd
+    cmd=$(HISTTIMEFORMAT= builtin history 1)
+    [[ $cmd =~ ^[[:space:]]*[0-9]+[*]?[[:space:]]+(.*)$ ]] && cmd=${BASH_REMATCH[1]}
+    cmd=${cmd//\%/%25}
+    cmd=${cmd//;/%3B}
+    cmd=${cmd//$'\n'/%0A}
+    cmd=${cmd//[$'\a\e']/}
+    printf '\033]133;C;cmdline_url=%s\a' "$cmd"
+  }
+  # Leave an existing DEBUG trap alone (e.g. bash-preexec); blocks then go unreported
+  [[ -z "$(trap -p DEBUG)" ]] && trap '_tp_preexec' DEBUG
 
-# ── Two-line prompt (Midnight Indigo palette) ──
-# Mint user · purple path · magenta (branch)
-# Blue ❯ cursor
-PS1='\n\[\033[38;2;200;245;220m\]\u\[\033[0m\] \[\033[38;2;139;125;255m\]\w\[\033[0m\]$(_tp_git_branch)\n\[\033[38;2;51;24;232m\]❯\[\033[0m\] '
+  if [[ $PROMPT_COMMAND != *_tp_precmd* ]]; then
+    PROMPT_COMMAND="_tp_precmd${PROMPT_COMMAND:+; $PROMPT_COMMAND}; _tp_prompt_ready"
+  fi
+fi
@@ -0,0 +1,87 @@
+# Terminal Plus — fish shell integration
+# Sourced through --init-command, after the user's config.fish.
+# TERMINAL_PLUS_INTEGRATION=marks_only keeps the user's prompt, colors and
+# aliases and only adds the cwd / command marks.
+
+# === Terminal Plus enhancements ===
+
+if test "$TERMINAL_PLUS_INTEGRATION" != marks_only
+
+    # ── Colors for ls, grep, etc. ──
+    set -gx CLICOLOR 1
+    set -gx CLICOLOR_FORCE 1
+    set -gx LSCOLORS GxFxCxDxBxegedabagaced
+    set -gx LS_COLORS 'di=1;36:ln=1;35:so=1;32:pi=1;33:ex=1;31:bd=34;46:cd=34;43:su=30;41:sg=30;46:tw=30;42:ow=34;43'
+
+    # Enable color in grep
+    function grep --wraps grep
+        command grep --color=auto $argv
+    end
+
+    # ── ls: append / to directories for Terminal+ link detection ──
+    # Replaces fish's own ls wrapper, which adds -F and its own colors
+    function ls --wraps ls
+        command ls -p $argv
+    end
+
+    # ── Prompt and syntax highlighting colors from the app theme ──
+    # theme.fish is generated by Terminal+ and sets the prompt colors and
+    # fish_color_*. It is re-sourced before the next prompt whenever its version
+    # line changes, so running shells follow theme switches.
+    set -g __terminal_plus_theme $HOME/.terminal-plus/shell/fish/theme.fish
+    function __terminal_plus_theme_check --on-event fish_prompt
+        test -r $__terminal_plus_theme; or return
+        read -l version < $__terminal_plus_theme
+        test "$version" = "set -g __terminal_plus_theme_version $__terminal_plus_theme_version"
+        or source $__terminal_plus_theme
+    end
+    __terminal_plus_theme_check
+
+    # ── Two-line prompt ──
+    # user · path · (branch)
+    # ❯ cursor
+    function fish_prompt
+        set -l dir $PWD
+        if test "$dir" = "$HOME"; or string match -q -- "$HOME/*" $dir
+            set dir "~"(string sub -s (math (string length -- $HOME) + 1) -- $dir)
+        end
+        echo
+        set_color $__terminal_plus_color_user; echo -n $USER; set_color normal
+        echo -n ' '
+        set_color $__terminal_plus_color_path; echo -n $dir; set_color normal
+        set -l branch (command git symbolic-ref --short HEAD 2>/dev/null)
+        if test -n "$branch"
+            set_color $__terminal_plus_color_branch; echo -n " ($branch)"; set_color normal
+        end
+        echo
+        set_color $__terminal_plus_color_prompt; echo -n '❯'; set_color normal
+        echo -n ' '
+    end
+
+    function fish_right_prompt
+        set_color $__terminal_plus_color_muted; date +%H:%M; set_color normal
+    end
+
+end
+
+# ── Report the working directory (OSC 7) so Terminal+ doesn't have to poll ──
+function __terminal_plus_osc7 --on-event fish_prompt
+    set -l url_path (string replace -a '%' '%25' -- $PWD | string replace -a ' ' '%20')
+    printf '\e]7;file://%s%s\a' $hostname $url_path
+end
+
+# ── Command marks (OSC 133) so Terminal+ can split output into command blocks ──
+# fish_postexec handlers still see the command's exit status in $status
+function __terminal_plus_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+function __terminal_plus_preexec --on-event fish_preexec
+    set -l cmd (string replace -a '%' '%25' -- $argv[1] | string replace -a ';' '%3B' | string replace -ra '[\a\e]' '' | string join '%0A')
+    printf '\e]133;C;cmdline_url=%s\a' $cmd
+end
+functions -c fish_prompt __terminal_plus_prompt
+function fish_prompt
+    printf '\e]133;A\a'
+    __terminal_plus_prompt
+    printf '\e]133;B\a'
+end
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+
+/// Emitted with a [`GroupMembership`] whenever a broadcast group changes.
+pub const GROUP_CHANGED_EVENT: &str = "pty-group-changed";
+
+/// Payload of the `pty-exit-{id}` event, emitted once the child has been reaped.
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyExit {
+    /// Exit code, if the process exited normally.
+    pub exit_code: Option<i32>,
+    /// Signal number, if the process was terminated by a signal.
+    pub signal: Option<i32>,
+    /// Signal name such as "SIGKILL".
+    pub signal_name: Option<String>,
+    /// Time between spawn and exit.
+    pub runtime_ms: u64,
+    /// Why the program couldn't be started again, for a failed restart.
+    pub error: Option<String>,
+}
+
+/// What `pty_destroy` had to do to stop the session.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TeardownOutcome {
+    /// The process had already exited before teardown started.
+    AlreadyExited,
+    /// The process exited within the grace period after SIGHUP.
+    Hangup,
+    /// The process ignored SIGHUP and was sent SIGKILL.
+    Killed,
+    /// There was no process to stop (e.g. a replay).
+    Closed,
+}
+
+/// Result of `pty_destroy`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyTeardown {
+    pub outcome: TeardownOutcome,
+    /// Exit status, if the child was reaped before teardown returned.
+    pub exit: Option<PtyExit>,
+    pub elapsed_ms: u64,
+}
+
+/// A running session, as listed by `pty_list_sessions`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub pid: Option<u32>,
+    pub cwd: String,
+    pub profile_id: String,
+    /// Unix time in milliseconds.
+    pub started_at: u64,
+    /// Set once the program has exited, for sessions held open afterwards.
+    pub exit: Option<PtyExit>,
+}
+
+/// The foreground job of a PTY, from `tcgetpgrp` on the master.
+#[derive(Debug, Clone, Serialize)]
+pub struct ForegroundProcess {
+    /// Process group leader pid.
+    pub pid: u32,
+    /// Executable name, e.g. "vim".
+    pub name: String,
+    /// Full argv, e.g. `["cargo", "test"]`.
+    pub cmdline: Vec<String>,
+    /// True when the shell itself is in the foreground (idle at the prompt).
+    pub is_shell: bool,
+}
+
+/// One command run at the prompt, delimited by OSC 133 marks.
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandBlock {
+    pub id: u64,
+    /// Command line as reported by the shell integration.
+    pub command: Option<String>,
+    /// Directory the command was started in.
+    pub cwd: Option<String>,
+    /// Unix time in milliseconds.
+    pub started_at: u64,
+    /// Unix time in milliseconds; `None` while the command is still running.
+    pub finished_at: Option<u64>,
+    pub exit_code: Option<i32>,
+    /// Stream offsets of the command's output.
+    pub output_start: u64,
+    pub output_end: Option<u64>,
+}
+
+/// Result of `pty_get_block_output`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockOutput {
+    pub block: CommandBlock,
+    /// The output with escape sequences removed.
+    pub text: String,
+    /// True when the start of the output has already been dropped from history.
+    pub truncated: bool,
+}
+
+/// A session recording, returned by `pty_start_recording` / `pty_stop_recording`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordingInfo {
+    /// The `.cast` file.
+    pub path: String,
+    /// Unix time in seconds, as in the cast header.
+    pub started_at: u64,
+    pub duration_ms: u64,
+}
+
+/// Playback state of a replay session; also the `pty-replay-state-{id}` payload.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayState {
+    pub position_ms: u64,
+    pub duration_ms: u64,
+    pub speed: f64,
+    pub paused: bool,
+    /// Every event has been played.
+    pub finished: bool,
+    /// Terminal size of the recording at the current position.
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// Lines to fetch from a session's screen model, `start..end`, where 0 is the
+/// oldest retained scrollback line.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Result of `pty_get_screen_text`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenText {
+    /// The requested lines, trailing blanks trimmed; with SGR sequences when styled.
+    pub lines: Vec<String>,
+    /// Index of the first returned line.
+    pub start: usize,
+    /// Scrollback plus visible lines; the visible screen is the last `rows` of them.
+    pub total_lines: usize,
+    pub scrollback_lines: usize,
+    pub cols: u16,
+    pub rows: u16,
+    pub cursor_row: u16,
+    pub cursor_col: u16,
+}
+
+/// Characters `start..end` of a line that matched a search.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A line of session history that matched `pty_search`. Soft-wrapped rows are
+/// joined into one line.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    /// Row number, since the session started, of the line's first row.
+    pub line: u64,
+    pub text: String,
+    pub ranges: Vec<MatchRange>,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+}
+
+/// Result of `pty_search`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    /// The newest matching lines, up to the limit, oldest first.
+    pub matches: Vec<SearchMatch>,
+    /// Matching lines in total, including those beyond the limit.
+    pub total_matches: usize,
+    /// Oldest row still searched; anything before it has been discarded.
+    pub first_line: u64,
+    /// Row number of index 0 in `pty_get_screen_text` ranges.
+    pub screen_first_line: u64,
+}
+
+/// Members of a broadcast group, sent as `pty-group-changed` whenever they change.
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupMembership {
+    pub group: String,
+    /// Session ids, sorted. Empty once the last member leaves.
+    pub members: Vec<String>,
+}
+
+/// What asked for a notification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    Bell,
+    /// `OSC 9 ; <body>` (iTerm2)
+    Osc9,
+    /// `OSC 777 ; notify ; <title> ; <body>` (urxvt, VTE)
+    Osc777,
+}
+
+/// Payload of the `pty-notify-{id}` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyNotification {
+    pub kind: NotificationKind,
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Which output monitor fired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorKind {
+    /// Output arrived after the session had been quiet for a while.
+    Activity,
+    /// No output has arrived for a while.
+    Silence,
+}
+
+/// Payload of the `pty-monitor-{id}` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorAlert {
+    pub kind: MonitorKind,
+    /// How long the session had been (or has been) quiet.
+    pub quiet_ms: u64,
+}
@@ -0,0 +1,76 @@
+//! `~/.terminal-plus/`, where the app keeps its settings and state, and the
+//! sections of its `settings.json`.
+
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+
+pub fn terminal_plus_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+    PathBuf::from(home).join(".terminal-plus")
+}
+
+/// Load the `key` section of `settings.json`, using the section's defaults
+/// when the file or the section doesn't exist.
+pub fn load_section<T: DeserializeOwned + Default>(key: &str) -> Result<T, String> {
+    let path = terminal_plus_dir().join("settings.json");
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => {
+            return Err(format!(
+                "Failed to read settings file {}: {e}",
+                path.display()
+            ))
+        }
+    };
+    parse_section(&contents, key)
+        .map_err(|e| format!("Invalid settings file {}: {e}", path.display()))
+}
+
+fn parse_section<T: DeserializeOwned + Default>(contents: &str, key: &str) -> Result<T, String> {
+    let mut file: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    match file.remove(key) {
+        Some(section) => serde_json::from_value(section).map_err(|e| format!("{key}: {e}")),
+        None => Ok(T::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, PartialEq, Deserialize)]
+    #[serde(default)]
+    struct Section {
+        enabled: bool,
+        limit: u32,
+    }
+
+    #[test]
+    fn reads_only_the_requested_section() {
+        let contents = r#"{"other": {"enabled": "not a bool"}, "section": {"limit": 3}}"#;
+        let section: Section = parse_section(contents, "section").unwrap();
+        assert_eq!(
+            section,
+            Section {
+                enabled: false,
+                limit: 3
+            }
+        );
+    }
+
+    #[test]
+    fn missing_section_uses_the_defaults() {
+        let section: Section = parse_section("{}", "section").unwrap();
+        assert_eq!(section, Section::default());
+    }
+
+    #[test]
+    fn invalid_section_names_the_key() {
+        let contents = r#"{"section": {"limit": "many"}}"#;
+        let err = parse_section::<Section>(contents, "section").unwrap_err();
+        assert!(err.starts_with("section: "), "{err}");
+    }
+}
@@ -0,0 +1,40 @@
+use tauri::ipc::Channel;
+use tauri::{AppHandle, State};
+
+use super::discover;
+use super::types::Task;
+use crate::pty::manager::PtyManager;
+use crate::pty::profile::ShellProfile;
+use crate::pty::scrollback::ScrollbackConfig;
+use crate::pty::session::{ExitPolicy, SpawnOptions};
+
+/// Tasks defined by the project around `cwd` (package.json, Cargo.toml,
+/// Makefile, justfile, pyproject.toml).
+#[tauri::command]
+pub async fn task_list(cwd: String) -> Result<Vec<Task>, String> {
+    discover::discover_tasks(&cwd)
+}
+
+/// Start `task` in a new session, as a command pane running in the task's directory.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn task_spawn(
+    app: AppHandle,
+    state: State<'_, PtyManager>,
+    session_id: String,
+    cols: u16,
+    rows: u16,
+    task: Task,
+    on_exit: Option<ExitPolicy>,
+    on_output: Channel,
+) -> Result<(), String> {
+    let options = SpawnOptions {
+        cols,
+        rows,
+        cwd: Some(task.cwd),
+        profile: ShellProfile::command(task.program, task.args),
+        scrollback: ScrollbackConfig::load()?,
+        on_exit: on_exit.unwrap_or_default(),
+    };
+    state.create_session(app, session_id, options, on_output)
+}
@@ -0,0 +1,603 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use super::types::{Task, TaskSource};
+
+/// Find the tasks available from `cwd`. For each kind of project file, the
+/// nearest one in `cwd` or its parents is used, stopping at the home directory.
+pub fn discover_tasks(cwd: &str) -> Result<Vec<Task>, String> {
+    let start = Path::new(cwd);
+    if !start.is_dir() {
+        return Err(format!("Not a directory: {cwd}"));
+    }
+
+    let mut tasks = Vec::new();
+    if let Some(file) = find_up(start, &["package.json"]) {
+        tasks.extend(npm_tasks(&file));
+    }
+    if let Some(file) = find_up(start, &["Cargo.toml"]) {
+        tasks.extend(cargo_tasks(&file));
+    }
+    if let Some(file) = find_up(start, &["GNUmakefile", "makefile", "Makefile"]) {
+        tasks.extend(make_tasks(&file));
+    }
+    if let Some(file) = find_up(start, &["justfile", "Justfile", ".justfile"]) {
+        tasks.extend(just_tasks(&file));
+    }
+    if let Some(file) = find_up(start, &["pyproject.toml"]) {
+        tasks.extend(python_tasks(&file));
+    }
+    Ok(tasks)
+}
+
+/// The first of `names` found in `start` or one of its parents.
+pub(crate) fn find_up(start: &Path, names: &[&str]) -> Option<PathBuf> {
+    let home = std::env::var("HOME").ok().map(PathBuf::from);
+    for dir in start.ancestors() {
+        if let Some(file) = names.iter().map(|n| dir.join(n)).find(|f| f.is_file()) {
+            return Some(file);
+        }
+        if home.as_deref() == Some(dir) {
+            break;
+        }
+    }
+    None
+}
+
+fn task(
+    file: &Path,
+    source: TaskSource,
+    name: impl Into<String>,
+    detail: Option<String>,
+    program: &str,
+    args: &[&str],
+) -> Task {
+    Task {
+        name: name.into(),
+        source,
+        detail,
+        program: program.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        cwd: file
+            .parent()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    }
+}
+
+/// `package.json` scripts, run with the package manager whose lockfile is present.
+fn npm_tasks(file: &Path) -> Vec<Task> {
+    let Some(json) = std::fs::read_to_string(file)
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+    else {
+        return Vec::new();
+    };
+    let Some(scripts) = json.get("scripts").and_then(|s| s.as_object()) else {
+        return Vec::new();
+    };
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let runner = [
+        ("pnpm-lock.yaml", "pnpm"),
+        ("yarn.lock", "yarn"),
+        ("bun.lockb", "bun"),
+        ("bun.lock", "bun"),
+    ]
+    .into_iter()
+    .find(|(lockfile, _)| dir.join(lockfile).is_file())
+    .map_or("npm", |(_, runner)| runner);
+
+    scripts
+        .iter()
+        .map(|(name, command)| {
+            task(
+                file,
+                TaskSource::Npm,
+                name,
+                command.as_str().map(str::to_string),
+                runner,
+                &["run", name],
+            )
+        })
+        .collect()
+}
+
+fn read_toml(file: &Path) -> Option<toml::Table> {
+    std::fs::read_to_string(file).ok()?.parse().ok()
+}
+
+/// Stems of `*.rs` files and names of subdirectories with a `main.rs` in `dir`.
+fn rust_targets_in(dir: &Path) -> BTreeSet<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return BTreeSet::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                path.file_stem().map(|s| s.to_string_lossy().into_owned())
+            } else if path.join("main.rs").is_file() {
+                path.file_name().map(|s| s.to_string_lossy().into_owned())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Names listed in `[[bin]]`-style arrays of tables.
+fn target_names(manifest: &toml::Table, key: &str) -> BTreeSet<String> {
+    manifest
+        .get(key)
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|t| t.get("name")?.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Build/test/check, a `run` per binary and example, and the `[alias]`es of
+/// the nearest `.cargo/config.toml`.
+fn cargo_tasks(file: &Path) -> Vec<Task> {
+    let Some(manifest) = read_toml(file) else {
+        return Vec::new();
+    };
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let cargo =
+        |name: String, args: &[&str]| task(file, TaskSource::Cargo, name, None, "cargo", args);
+
+    let mut tasks = vec![
+        cargo("build".to_string(), &["build"]),
+        cargo("test".to_string(), &["test"]),
+        cargo("check".to_string(), &["check"]),
+    ];
+
+    if let Some(package) = manifest
+        .get("package")
+        .and_then(|p| p.get("name")?.as_str())
+    {
+        let mut bins = target_names(&manifest, "bin");
+        if dir.join("src/main.rs").is_file() {
+            bins.insert(package.to_string());
+        }
+        bins.extend(rust_targets_in(&dir.join("src/bin")));
+        if bins.len() == 1 {
+            tasks.push(cargo("run".to_string(), &["run"]));
+        } else {
+            for bin in &bins {
+                tasks.push(cargo(format!("run --bin {bin}"), &["run", "--bin", bin]));
+            }
+        }
+
+        let mut examples = target_names(&manifest, "example");
+        examples.extend(rust_targets_in(&dir.join("examples")));
+        for example in &examples {
+            tasks.push(cargo(
+                format!("run --example {example}"),
+                &["run", "--example", example],
+            ));
+        }
+    }
+
+    let aliases = find_up(dir, &[".cargo/config.toml", ".cargo/config"])
+        .and_then(|config| read_toml(&config))
+        .and_then(|config| config.get("alias")?.as_table().cloned())
+        .unwrap_or_default();
+    for (alias, expansion) in &aliases {
+        let detail = match expansion {
+            toml::Value::String(s) => Some(format!("cargo {s}")),
+            toml::Value::Array(args) => Some(format!(
+                "cargo {}",
+                args.iter()
+                    .filter_map(|a| a.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )),
+            _ => None,
+        };
+        tasks.push(task(
+            file,
+            TaskSource::Cargo,
+            alias,
+            detail,
+            "cargo",
+            &[alias],
+        ));
+    }
+    tasks
+}
+
+/// Explicit targets of a Makefile. Pattern rules, variables and special
+/// targets (`.PHONY`) are skipped; a trailing `## comment` becomes the detail.
+fn make_tasks(file: &Path) -> Vec<Task> {
+    let Ok(contents) = std::fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    let mut seen = BTreeSet::new();
+    let mut tasks = Vec::new();
+    for line in contents.lines() {
+        if line.starts_with(['\t', ' ', '#', '.']) {
+            continue;
+        }
+        let Some((targets, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `VAR := value`, `VAR ::= value`, `VAR = a:b`
+        if rest.trim_start_matches(':').starts_with('=') || targets.contains('=') {
+            continue;
+        }
+        let detail = rest
+            .split_once("##")
+            .map(|(_, comment)| comment.trim().to_string());
+        for target in targets.split_whitespace() {
+            if target.contains(['$', '%', '/']) || !seen.insert(target.to_string()) {
+                continue;
+            }
+            tasks.push(task(
+                file,
+                TaskSource::Make,
+                target,
+                detail.clone(),
+                "make",
+                &[target],
+            ));
+        }
+    }
+    tasks
+}
+
+/// Public recipes of a justfile, with the comment above each as its detail.
+fn just_tasks(file: &Path) -> Vec<Task> {
+    let Ok(contents) = std::fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    let mut tasks = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+    for line in contents.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim().to_string());
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+        if line.trim().is_empty() || line.starts_with([' ', '\t']) {
+            if line.trim().is_empty() {
+                comment = None;
+                private = false;
+            }
+            continue;
+        }
+        let (doc, hidden) = (comment.take(), std::mem::take(&mut private));
+
+        let Some((head, rest)) = line.trim_start_matches('@').split_once(':') else {
+            continue;
+        };
+        // Assignments and settings: `x := ...`, `set shell := ...`, `alias b := build`
+        if rest.starts_with('=') {
+            continue;
+        }
+        let Some(name) = head.split_whitespace().next() else {
+            continue;
+        };
+        let is_recipe = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !is_recipe || hidden || name.starts_with('_') {
+            continue;
+        }
+        tasks.push(task(file, TaskSource::Just, name, doc, "just", &[name]));
+    }
+    tasks
+}
+
+/// `[project.scripts]`, `[tool.poetry.scripts]` and `[tool.pdm.scripts]`,
+/// run through the project's tool when it has a lockfile.
+fn python_tasks(file: &Path) -> Vec<Task> {
+    let Some(pyproject) = read_toml(file) else {
+        return Vec::new();
+    };
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let table = |path: &[&str]| {
+        path.iter()
+            .try_fold(&pyproject, |table, key| table.get(*key)?.as_table())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let runner = [
+        ("uv.lock", "uv"),
+        ("poetry.lock", "poetry"),
+        ("pdm.lock", "pdm"),
+    ]
+    .into_iter()
+    .find(|(lockfile, _)| dir.join(lockfile).is_file())
+    .map(|(_, runner)| runner);
+
+    let mut seen = BTreeSet::new();
+    let mut tasks = Vec::new();
+    for scripts in [
+        table(&["project", "scripts"]),
+        table(&["tool", "poetry", "scripts"]),
+    ] {
+        for (name, entry_point) in &scripts {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let detail = entry_point.as_str().map(str::to_string);
+            tasks.push(match runner {
+                Some(runner) => task(
+                    file,
+                    TaskSource::Python,
+                    name,
+                    detail,
+                    runner,
+                    &["run", name],
+                ),
+                None => task(file, TaskSource::Python, name, detail, name, &[]),
+            });
+        }
+    }
+    for (name, script) in &table(&["tool", "pdm", "scripts"]) {
+        // `_` holds options shared by all scripts
+        if name == "_" || !seen.insert(name.clone()) {
+            continue;
+        }
+        let detail = match script {
+            toml::Value::String(s) => Some(s.clone()),
+            toml::Value::Table(t) => ["cmd", "shell", "call"]
+                .iter()
+                .find_map(|key| t.get(*key)?.as_str().map(str::to_string)),
+            _ => None,
+        };
+        tasks.push(task(
+            file,
+            TaskSource::Python,
+            name,
+            detail,
+            "pdm",
+            &["run", name],
+        ));
+    }
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch project directory with the given files, removed on drop.
+    struct Project(PathBuf);
+
+    impl Project {
+        fn new(files: &[(&str, &str)]) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "terminal-plus-tasks-{}",
+                uuid::Uuid::new_v4().simple()
+            ));
+            for (name, contents) in files {
+                let path = dir.join(name);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, contents).unwrap();
+            }
+            Project(dir)
+        }
+
+        fn file(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn names(tasks: &[Task]) -> Vec<&str> {
+        tasks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    fn find<'a>(tasks: &'a [Task], name: &str) -> &'a Task {
+        tasks
+            .iter()
+            .find(|t| t.name == name)
+            .unwrap_or_else(|| panic!("no task {name} in {:?}", names(tasks)))
+    }
+
+    #[test]
+    fn make_targets() {
+        let project = Project::new(&[(
+            "Makefile",
+            "VAR := x\n\
+             CC = gcc\n\
+             OTHER ::= y\n\
+             URL = http://example.com\n\
+             .PHONY: all build test\n\
+             all: build test ## Build and test everything\n\
+             build: ## Compile\n\
+             \tcc -o app main.c\n\
+             a:: b\n\
+             %.o: %.c\n\
+             \t$(CC) -c $<\n\
+             $(OUT): x\n\
+             dir/file: y\n\
+             test lint: ## Check\n\
+             # build: commented out\n\
+             build: more\n",
+        )]);
+        let tasks = make_tasks(&project.file("Makefile"));
+        assert_eq!(names(&tasks), ["all", "build", "a", "test", "lint"]);
+        assert_eq!(
+            find(&tasks, "all").detail.as_deref(),
+            Some("Build and test everything")
+        );
+        assert_eq!(find(&tasks, "build").detail.as_deref(), Some("Compile"));
+        assert_eq!(find(&tasks, "a").detail, None);
+        assert_eq!(find(&tasks, "lint").detail.as_deref(), Some("Check"));
+
+        let build = find(&tasks, "build");
+        assert_eq!(build.program, "make");
+        assert_eq!(build.args, ["build"]);
+        assert_eq!(build.cwd, project.0.to_string_lossy());
+    }
+
+    #[test]
+    fn just_recipes() {
+        let project = Project::new(&[(
+            "justfile",
+            "set shell := [\"bash\", \"-c\"]\n\
+             version := \"1.0\"\n\
+             alias b := build\n\
+             \n\
+             # Build the app\n\
+             build target=\"a:b\":\n\
+             \x20   cargo build\n\
+             \n\
+             [private]\n\
+             secret:\n\
+             \x20   echo hi\n\
+             \n\
+             _hidden:\n\
+             \x20   echo hidden\n\
+             \n\
+             # Run the tests\n\
+             @test *args: build\n\
+             \x20   cargo test {{args}}\n\
+             \n\
+             [group('ci')]\n\
+             [private]\n\
+             lint:\n\
+             \x20   cargo clippy\n\
+             \n\
+             # Not about the next recipe\n\
+             \n\
+             fmt:\n\
+             \x20   cargo fmt\n",
+        )]);
+        let tasks = just_tasks(&project.file("justfile"));
+        assert_eq!(names(&tasks), ["build", "test", "fmt"]);
+        assert_eq!(
+            find(&tasks, "build").detail.as_deref(),
+            Some("Build the app")
+        );
+        assert_eq!(
+            find(&tasks, "test").detail.as_deref(),
+            Some("Run the tests")
+        );
+        assert_eq!(find(&tasks, "fmt").detail, None);
+        assert_eq!(find(&tasks, "test").args, ["test"]);
+    }
+
+    #[test]
+    fn cargo_with_a_single_binary() {
+        let project = Project::new(&[
+            ("Cargo.toml", "[package]\nname = \"app\"\n"),
+            ("src/main.rs", "fn main() {}\n"),
+        ]);
+        let tasks = cargo_tasks(&project.file("Cargo.toml"));
+        assert_eq!(names(&tasks), ["build", "test", "check", "run"]);
+        assert_eq!(find(&tasks, "run").args, ["run"]);
+    }
+
+    #[test]
+    fn cargo_with_several_binaries_examples_and_aliases() {
+        let project = Project::new(&[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"app\"\n\n[[bin]]\nname = \"extra\"\npath = \"extra.rs\"\n",
+            ),
+            ("src/main.rs", "fn main() {}\n"),
+            ("src/bin/tool.rs", "fn main() {}\n"),
+            ("src/bin/server/main.rs", "fn main() {}\n"),
+            ("examples/demo.rs", "fn main() {}\n"),
+            (
+                ".cargo/config.toml",
+                "[alias]\nb = \"build --release\"\nt = [\"test\", \"--all\"]\n",
+            ),
+        ]);
+        let tasks = cargo_tasks(&project.file("Cargo.toml"));
+        let mut runs: Vec<_> = names(&tasks)
+            .into_iter()
+            .filter(|name| name.starts_with("run"))
+            .collect();
+        runs.sort_unstable();
+        assert_eq!(
+            runs,
+            [
+                "run --bin app",
+                "run --bin extra",
+                "run --bin server",
+                "run --bin tool",
+                "run --example demo",
+            ]
+        );
+        assert_eq!(
+            find(&tasks, "run --bin tool").args,
+            ["run", "--bin", "tool"]
+        );
+        assert_eq!(
+            find(&tasks, "b").detail.as_deref(),
+            Some("cargo build --release")
+        );
+        assert_eq!(
+            find(&tasks, "t").detail.as_deref(),
+            Some("cargo test --all")
+        );
+        assert_eq!(find(&tasks, "t").args, ["t"]);
+    }
+
+    #[test]
+    fn cargo_workspace_without_a_package() {
+        let project = Project::new(&[("Cargo.toml", "[workspace]\nmembers = [\"a\"]\n")]);
+        let tasks = cargo_tasks(&project.file("Cargo.toml"));
+        assert_eq!(names(&tasks), ["build", "test", "check"]);
+    }
+
+    #[test]
+    fn python_scripts() {
+        let project = Project::new(&[
+            (
+                "pyproject.toml",
+                "[project.scripts]\n\
+                 serve = \"app:main\"\n\
+                 \n\
+                 [tool.poetry.scripts]\n\
+                 serve = \"other:main\"\n\
+                 cli = \"app.cli:run\"\n\
+                 \n\
+                 [tool.pdm.scripts]\n\
+                 _ = { env_file = \".env\" }\n\
+                 lint = { cmd = \"ruff check .\" }\n\
+                 fmt = \"black .\"\n",
+            ),
+            ("uv.lock", ""),
+        ]);
+        let tasks = python_tasks(&project.file("pyproject.toml"));
+        let mut all = names(&tasks);
+        all.sort_unstable();
+        assert_eq!(all, ["cli", "fmt", "lint", "serve"]);
+
+        let serve = find(&tasks, "serve");
+        assert_eq!(serve.detail.as_deref(), Some("app:main"));
+        assert_eq!(serve.program, "uv");
+        assert_eq!(serve.args, ["run", "serve"]);
+        let lint = find(&tasks, "lint");
+        assert_eq!(lint.detail.as_deref(), Some("ruff check ."));
+        assert_eq!(lint.program, "pdm");
+        assert_eq!(lint.args, ["run", "lint"]);
+    }
+
+    #[test]
+    fn python_scripts_without_a_lockfile_run_directly() {
+        let project = Project::new(&[(
+            "pyproject.toml",
+            "[project.scripts]\nserve = \"app:main\"\n",
+        )]);
+        let tasks = python_tasks(&project.file("pyproject.toml"));
+        assert_eq!(tasks[0].program, "serve");
+        assert!(tasks[0].args.is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of project file a task was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskSource {
+    /// `package.json` scripts
+    Npm,
+    /// `Cargo.toml` targets and `.cargo/config.toml` aliases
+    Cargo,
+    Make,
+    Just,
+    /// `pyproject.toml` scripts
+    Python,
+}
+
+/// A runnable task discovered in a project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    /// Label for the launcher, e.g. `dev` or `run --bin server`.
+    pub name: String,
+    pub source: TaskSource,
+    /// What the task does: the script's command line, a recipe's doc comment, ...
+    pub detail: Option<String>,
+    pub program: String,
+    pub args: Vec<String>,
+    /// Directory of the file that defines the task; it runs there.
+    pub cwd: String,
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+
+use crate::workspace::types::{SplitDirection, TileNode};
+
+/// A cell of a tmux window layout, e.g. `b25f,80x24,0,0{40x24,0,0,1,39x24,41,0,2}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutCell {
+    pub cols: u16,
+    pub rows: u16,
+    pub kind: CellKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CellKind {
+    Pane(u32),
+    /// `{...}`: side by side.
+    LeftRight(Vec<LayoutCell>),
+    /// `[...]`: stacked.
+    TopBottom(Vec<LayoutCell>),
+}
+
+/// Parse a window layout as tmux prints it, checksum first.
+pub fn parse(layout: &str) -> Result<LayoutCell, String> {
+    let body = layout
+        .split_once(',')
+        .map(|(_, body)| body)
+        .ok_or_else(|| format!("Invalid tmux layout {layout}"))?;
+    let mut parser = Parser {
+        input: body.as_bytes(),
+        pos: 0,
+    };
+    let cell = parser
+        .cell()
+        .ok_or_else(|| format!("Invalid tmux layout {layout}"))?;
+    if parser.pos != body.len() {
+        return Err(format!("Invalid tmux layout {layout}"));
+    }
+    Ok(cell)
+}
+
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// `<cols>x<rows>,<x>,<y>` then `,<pane>`, `{cells}` or `[cells]`.
+    fn cell(&mut self) -> Option<LayoutCell> {
+        let cols = self.number()?;
+        self.expect(b'x')?;
+        let rows = self.number()?;
+        self.expect(b',')?;
+        self.number()?;
+        self.expect(b',')?;
+        self.number()?;
+        let kind = match self.input.get(self.pos)? {
+            b',' => {
+                self.pos += 1;
+                CellKind::Pane(self.number()?)
+            }
+            b'{' => CellKind::LeftRight(self.children(b'}')?),
+            b'[' => CellKind::TopBottom(self.children(b']')?),
+            _ => return None,
+        };
+        Some(LayoutCell {
+            cols: u16::try_from(cols).ok()?,
+            rows: u16::try_from(rows).ok()?,
+            kind,
+        })
+    }
+
+    fn children(&mut self, close: u8) -> Option<Vec<LayoutCell>> {
+        self.pos += 1;
+        let mut children = vec![self.cell()?];
+        while self.input.get(self.pos) == Some(&b',') {
+            self.pos += 1;
+            children.push(self.cell()?);
+        }
+        self.expect(close)?;
+        Some(children)
+    }
+
+    fn number(&mut self) -> Option<u32> {
+        let start = self.pos;
+        while self.input.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.input.get(self.pos) == Some(&byte)).then(|| self.pos += 1)
+    }
+}
+
+impl LayoutCell {
+    /// Pane ids, left to right and top to bottom.
+    pub fn panes(&self) -> Vec<u32> {
+        match &self.kind {
+            CellKind::Pane(pane) => vec![*pane],
+            CellKind::LeftRight(children) | CellKind::TopBottom(children) => {
+                children.iter().flat_map(LayoutCell::panes).collect()
+            }
+        }
+    }
+
+    pub fn find(&self, pane: u32) -> Option<&LayoutCell> {
+        match &self.kind {
+            CellKind::Pane(id) => (*id == pane).then_some(self),
+            CellKind::LeftRight(children) | CellKind::TopBottom(children) => {
+                children.iter().find_map(|child| child.find(pane))
+            }
+        }
+    }
+
+    /// The size this cell would need for its panes to have the sizes in
+    /// `sizes`, keeping tmux's one-cell borders between them. Panes missing
+    /// from `sizes` keep their current size.
+    pub fn size_for(&self, sizes: &HashMap<u32, (u16, u16)>) -> (u16, u16) {
+        match &self.kind {
+            CellKind::Pane(pane) => sizes.get(pane).copied().unwrap_or((self.cols, self.rows)),
+            CellKind::LeftRight(children) => {
+                let sizes: Vec<_> = children.iter().map(|c| c.size_for(sizes)).collect();
+                let cols = sizes
+                    .iter()
+                    .map(|s| s.0)
+                    .fold(0u16, u16::saturating_add)
+                    .saturating_add(borders(&sizes));
+                (cols, sizes.iter().map(|s| s.1).max().unwrap_or(0))
+            }
+            CellKind::TopBottom(children) => {
+                let sizes: Vec<_> = children.iter().map(|c| c.size_for(sizes)).collect();
+                let rows = sizes
+                    .iter()
+                    .map(|s| s.1)
+                    .fold(0u16, u16::saturating_add)
+                    .saturating_add(borders(&sizes));
+                (sizes.iter().map(|s| s.0).max().unwrap_or(0), rows)
+            }
+        }
+    }
+
+    /// The frontend's tile tree for this cell. Ids are derived from the panes,
+    /// so an unchanged layout gives an identical tree.
+    pub fn to_tile(&self, session_id: &impl Fn(u32) -> String, depth: usize) -> TileNode {
+        match &self.kind {
+            CellKind::Pane(pane) => {
+                let session_id = session_id(*pane);
+                TileNode::Leaf {
+                    id: format!("{session_id}-tile"),
+                    session_id,
+                }
+            }
+            CellKind::LeftRight(children) => {
+                nest(children, SplitDirection::Vertical, session_id, depth)
+            }
+            CellKind::TopBottom(children) => {
+                nest(children, SplitDirection::Horizontal, session_id, depth)
+            }
+        }
+    }
+}
+
+/// The one-cell borders between `cells` side by side or stacked.
+fn borders<T>(cells: &[T]) -> u16 {
+    u16::try_from(cells.len().saturating_sub(1)).unwrap_or(u16::MAX)
+}
+
+/// Nest tmux's n-ary split into binary ones: the first child, then a split of
+/// the rest, sized as tmux has them.
+fn nest(
+    children: &[LayoutCell],
+    direction: SplitDirection,
+    session_id: &impl Fn(u32) -> String,
+    depth: usize,
+) -> TileNode {
+    let [first, rest @ ..] = children else {
+        unreachable!("tmux splits have at least one child");
+    };
+    if rest.is_empty() {
+        return first.to_tile(session_id, depth);
+    }
+    let extent = |cell: &LayoutCell| match direction {
+        SplitDirection::Vertical => f64::from(cell.cols),
+        SplitDirection::Horizontal => f64::from(cell.rows),
+    };
+    let total: f64 = children.iter().map(extent).sum();
+    let first_pane = first.panes()[0];
+    TileNode::Split {
+        id: format!("{}-split-{depth}", session_id(first_pane)),
+        direction,
+        ratio: extent(first) / total,
+        children: Box::new([
+            first.to_tile(session_id, depth + 1),
+            nest(rest, direction, session_id, depth + 1),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two panes side by side, the right one split top and bottom, as
+    /// `list-windows` prints it.
+    const NESTED: &str = "bb62,159x48,0,0{79x48,0,0,0,79x48,80,0[79x24,80,0,1,79x23,80,25,2]}";
+
+    fn pane(cols: u16, rows: u16, id: u32) -> LayoutCell {
+        LayoutCell {
+            cols,
+            rows,
+            kind: CellKind::Pane(id),
+        }
+    }
+
+    #[test]
+    fn parses_a_single_pane() {
+        assert_eq!(parse("b25f,80x24,0,0,3"), Ok(pane(80, 24, 3)));
+    }
+
+    #[test]
+    fn parses_nested_splits() {
+        let cell = parse(NESTED).unwrap();
+        assert_eq!(
+            cell,
+            LayoutCell {
+                cols: 159,
+                rows: 48,
+                kind: CellKind::LeftRight(vec![
+                    pane(79, 48, 0),
+                    LayoutCell {
+                        cols: 79,
+                        rows: 48,
+                        kind: CellKind::TopBottom(vec![pane(79, 24, 1), pane(79, 23, 2)]),
+                    },
+                ]),
+            }
+        );
+        assert_eq!(cell.panes(), vec![0, 1, 2]);
+        assert_eq!(cell.find(2), Some(&pane(79, 23, 2)));
+        assert_eq!(cell.find(7), None);
+    }
+
+    #[test]
+    fn skips_the_checksum_without_checking_it() {
+        assert_eq!(parse("0000,80x24,0,0,3"), Ok(pane(80, 24, 3)));
+    }
+
+    #[test]
+    fn rejects_malformed_layouts() {
+        for layout in [
+            "",
+            "80x24,0,0,3",
+            "b25f,",
+            "b25f,80x24,0,0",
+            "b25f,80x24,0,0,3,",
+            "b25f,80x24,0,0{40x24,0,0,1,39x24,41,0,2",
+            "b25f,80x24,0,0{40x24,0,0,1]",
+            "b25f,80x24,0,0[]",
+            "b25f,70000x24,0,0,3",
+        ] {
+            assert!(parse(layout).is_err(), "{layout:?} parsed");
+        }
+    }
+
+    #[test]
+    fn size_for_keeps_borders_between_panes() {
+        let cell = parse(NESTED).unwrap();
+        assert_eq!(cell.size_for(&HashMap::new()), (159, 48));
+
+        let sizes = HashMap::from([(0, (100, 40)), (2, (79, 30))]);
+        assert_eq!(cell.size_for(&sizes), (180, 55));
+    }
+
+    #[test]
+    fn size_for_saturates() {
+        let cell = LayoutCell {
+            cols: 80,
+            rows: 24,
+            kind: CellKind::LeftRight(vec![pane(40, 24, 1), pane(39, 24, 2)]),
+        };
+        let sizes = HashMap::from([(1, (u16::MAX, 24)), (2, (u16::MAX, 24))]);
+        assert_eq!(cell.size_for(&sizes), (u16::MAX, 24));
+
+        let cell = LayoutCell {
+            cols: 80,
+            rows: 24,
+            kind: CellKind::TopBottom(vec![pane(80, 12, 1), pane(80, 11, 2)]),
+        };
+        let sizes = HashMap::from([(1, (80, u16::MAX))]);
+        assert_eq!(cell.size_for(&sizes), (80, u16::MAX));
+    }
+
+    #[test]
+    fn to_tile_nests_splits_with_tmux_sizes() {
+        let cell = parse(NESTED).unwrap();
+        let tile = cell.to_tile(&|pane| format!("s-{pane}"), 0);
+        let TileNode::Split {
+            id,
+            direction: SplitDirection::Vertical,
+            ratio,
+            children,
+        } = tile
+        else {
+            panic!("expected a side by side split");
+        };
+        assert_eq!(id, "s-0-split-0");
+        assert!((ratio - 0.5).abs() < 1e-9);
+        let [TileNode::Leaf { session_id, .. }, TileNode::Split {
+            direction: SplitDirection::Horizontal,
+            ratio,
+            ..
+        }] = *children
+        else {
+            panic!("expected a pane and a stacked split");
+        };
+        assert_eq!(session_id, "s-0");
+        assert!((ratio - 24.0 / 47.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,461 @@
+//! tmux control mode: when a session runs `tmux -CC` (locally or over ssh),
+//! its output switches to tmux's line protocol. [`ControlMode`] takes that
+//! over in the session's reader thread, shows each tmux pane as a session of
+//! its own ([`pane::TmuxPane`]) and sends input and resizes back as tmux
+//! commands. The frontend gets the panes as a tile tree in `tmux-layout`.
+
+pub mod layout;
+pub mod pane;
+pub mod protocol;
+pub mod types;
+
+use parking_lot::Mutex;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::pty::manager::PtyManager;
+use crate::pty::session::PtyWriter;
+use crate::workspace::types::{SplitDirection, TileNode};
+use layout::LayoutCell;
+use pane::TmuxPane;
+use protocol::{Notification, DCS_START};
+use types::TmuxLayout;
+
+/// Lines of history fetched for a pane when it first appears.
+const CAPTURE_HISTORY: usize = 1000;
+
+/// The format subscription that keeps each pane's cwd up to date.
+const CWD_SUBSCRIPTION: &str = "cwd";
+
+/// Input bytes per `send-keys` command.
+const SEND_KEYS_CHUNK: usize = 256;
+
+/// What to do with the reply to a command, in the order they were sent.
+enum Reply {
+    Ignore,
+    /// `list-windows`: every window's layout.
+    Windows,
+    Cursor(u32),
+    Capture(u32),
+}
+
+/// The gateway session's side of the connection, shared with its panes:
+/// sends commands into the PTY and remembers what each reply is for.
+pub struct Gateway {
+    writer: PtyWriter,
+    pending: Mutex<VecDeque<Reply>>,
+    windows: Mutex<Windows>,
+}
+
+#[derive(Default)]
+struct Windows {
+    layouts: BTreeMap<u32, LayoutCell>,
+    /// Pane sizes the frontend asked for since tmux last sent a layout.
+    requested: HashMap<u32, (u16, u16)>,
+}
+
+impl Gateway {
+    fn command(&self, command: &str, reply: Reply) -> Result<(), String> {
+        use std::io::Write;
+        // Queued and written together, so replies can't be matched out of order
+        let mut pending = self.pending.lock();
+        pending.push_back(reply);
+        let mut writer = self.writer.lock();
+        let written = writer
+            .write_all(format!("{command}\n").as_bytes())
+            .and_then(|()| writer.flush());
+        if let Err(e) = written {
+            pending.pop_back();
+            return Err(format!("Failed to send tmux command: {e}"));
+        }
+        Ok(())
+    }
+
+    fn send_keys(&self, pane: u32, data: &[u8]) -> Result<(), String> {
+        for chunk in data.chunks(SEND_KEYS_CHUNK) {
+            let keys: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            self.command(
+                &format!("send-keys -t %{pane} -H {}", keys.join(" ")),
+                Reply::Ignore,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Resize the pane's window so the pane can be `cols` x `rows` next to
+    /// its neighbours' requested sizes, then the pane itself.
+    fn resize_pane(&self, pane: u32, cols: u16, rows: u16) -> Result<(), String> {
+        let mut commands = Vec::new();
+        {
+            let mut windows = self.windows.lock();
+            windows.requested.insert(pane, (cols, rows));
+            let Windows { layouts, requested } = &*windows;
+            let Some((window, root)) = layouts.iter().find(|(_, l)| l.find(pane).is_some()) else {
+                return Ok(());
+            };
+            let (width, height) = root.size_for(requested);
+            if (width, height) != (root.cols, root.rows) {
+                commands.push(format!("resize-window -t @{window} -x {width} -y {height}"));
+            }
+            if root
+                .find(pane)
+                .is_some_and(|cell| (cell.cols, cell.rows) != (cols, rows))
+            {
+                commands.push(format!("resize-pane -t %{pane} -x {cols} -y {rows}"));
+            }
+        }
+        for command in commands {
+            self.command(&command, Reply::Ignore)?;
+        }
+        Ok(())
+    }
+
+    /// Fetch the pane's screen, and `history` lines above it, for
+    /// [`TmuxPane::captured`].
+    fn capture_pane(&self, pane: u32, history: usize) -> Result<(), String> {
+        // The cursor first: capture-pane's reply is what ends the wait for both
+        self.command(
+            &format!("display-message -p -t %{pane} '#{{cursor_x}} #{{cursor_y}}'"),
+            Reply::Cursor(pane),
+        )?;
+        self.command(
+            &format!("capture-pane -p -e -t %{pane} -S -{history}"),
+            Reply::Capture(pane),
+        )
+    }
+
+    /// Stop tmux sending a pane's output, or start it again; what the pane
+    /// prints meanwhile is dropped. Needs tmux 3.2; older servers reply with
+    /// an error, which is ignored.
+    fn set_pane_output(&self, pane: u32, on: bool) -> Result<(), String> {
+        let action = if on { "on" } else { "off" };
+        self.command(
+            &format!("refresh-client -A '%{pane}:{action}'"),
+            Reply::Ignore,
+        )
+    }
+
+    fn kill_pane(&self, pane: u32) -> Result<(), String> {
+        self.command(&format!("kill-pane -t %{pane}"), Reply::Ignore)
+    }
+}
+
+/// Watches a session's output for tmux control mode, in its reader thread.
+pub struct ControlMode {
+    app: AppHandle,
+    session_id: String,
+    writer: PtyWriter,
+    /// The end of the last plain output, in case `DCS_START` spans two reads.
+    tail: Vec<u8>,
+    client: Option<Client>,
+}
+
+/// State while in control mode.
+struct Client {
+    app: AppHandle,
+    /// The gateway session's id.
+    session_id: String,
+    gateway: Arc<Gateway>,
+    line: Vec<u8>,
+    /// The reply being read, between `%begin` and `%end`.
+    block: Option<(Reply, Vec<String>)>,
+    panes: HashMap<u32, Arc<TmuxPane>>,
+    cursors: HashMap<u32, (u16, u16)>,
+}
+
+impl ControlMode {
+    pub fn new(app: AppHandle, session_id: String, writer: PtyWriter) -> Self {
+        ControlMode {
+            app,
+            session_id,
+            writer,
+            tail: Vec::new(),
+            client: None,
+        }
+    }
+
+    /// Take the control mode parts out of `data`, returning the rest: output
+    /// from before tmux started or after it exited. The DCS that wraps control
+    /// mode is passed through, so the terminal sees a complete, empty sequence.
+    pub fn feed<'a>(&mut self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        let mut plain = Cow::Borrowed(&[][..]);
+        let mut rest = data;
+        while !rest.is_empty() {
+            if let Some(client) = &mut self.client {
+                let (done, after) = client.feed(rest);
+                if done {
+                    self.finish();
+                }
+                rest = after;
+                continue;
+            }
+            let end = self.find_start(rest);
+            let (before, after) = rest.split_at(end.unwrap_or(rest.len()));
+            if plain.is_empty() {
+                plain = Cow::Borrowed(before);
+            } else {
+                plain.to_mut().extend_from_slice(before);
+            }
+            if end.is_some() {
+                self.start();
+            }
+            rest = after;
+        }
+        plain
+    }
+
+    /// The session's output has ended; so has control mode.
+    pub fn close(&mut self) {
+        if self.client.is_some() {
+            self.finish();
+        }
+    }
+
+    /// Where `DCS_START` ends in `data`, counting a start in the tail of the
+    /// previous read.
+    fn find_start(&mut self, data: &[u8]) -> Option<usize> {
+        let found = data
+            .windows(DCS_START.len())
+            .position(|w| w == DCS_START)
+            .map(|i| i + DCS_START.len())
+            .or_else(|| {
+                (1..DCS_START.len()).find_map(|k| {
+                    let (head, rest) = DCS_START.split_at(k);
+                    (self.tail.ends_with(head) && data.starts_with(rest)).then_some(rest.len())
+                })
+            });
+        self.tail.extend_from_slice(data);
+        let keep = self.tail.len().saturating_sub(DCS_START.len() - 1);
+        self.tail.drain(..keep);
+        found
+    }
+
+    fn start(&mut self) {
+        self.tail.clear();
+        let client = Client {
+            app: self.app.clone(),
+            session_id: self.session_id.clone(),
+            gateway: Arc::new(Gateway {
+                writer: self.writer.clone(),
+                pending: Mutex::new(VecDeque::new()),
+                windows: Mutex::new(Windows::default()),
+            }),
+            line: Vec::new(),
+            block: None,
+            panes: HashMap::new(),
+            cursors: HashMap::new(),
+        };
+        client.list_windows();
+        // tmux sends every pane's value, then each change (checked once a second)
+        let _ = client.gateway.command(
+            &format!("refresh-client -B '{CWD_SUBSCRIPTION}:%*:#{{pane_current_path}}'"),
+            Reply::Ignore,
+        );
+        self.client = Some(client);
+    }
+
+    /// Leave control mode: the panes are closed and the gateway shown again.
+    fn finish(&mut self) {
+        if let Some(client) = self.client.take() {
+            for pane in client.panes.values() {
+                pane.close();
+            }
+        }
+        emit(&self.app, &self.session_id, None);
+    }
+}
+
+impl Client {
+    /// Handle complete lines. Returns whether tmux left control mode, and
+    /// what follows if it did.
+    fn feed<'a>(&mut self, data: &'a [u8]) -> (bool, &'a [u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let mut line = std::mem::take(&mut self.line);
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if self.line(&line) {
+                return (true, &data[i + 1..]);
+            }
+        }
+        (false, &[])
+    }
+
+    /// Handle one line; true once tmux has left control mode.
+    fn line(&mut self, line: &[u8]) -> bool {
+        if let Some((reply, mut lines)) = self.block.take() {
+            if protocol::is_block_end(line) {
+                self.reply(reply, lines, line.starts_with(b"%end"));
+            } else {
+                lines.push(String::from_utf8_lossy(line).into_owned());
+                self.block = Some((reply, lines));
+            }
+            return false;
+        }
+        match protocol::parse_notification(line) {
+            Notification::Begin { ours } => {
+                // Only replies to our commands are queued; not the one that started tmux
+                let reply = if ours {
+                    self.gateway.pending.lock().pop_front()
+                } else {
+                    None
+                };
+                self.block = Some((reply.unwrap_or(Reply::Ignore), Vec::new()));
+            }
+            Notification::Output { pane, data } => {
+                if let Some(pane) = self.panes.get(&pane) {
+                    pane.output(&data);
+                }
+            }
+            Notification::LayoutChange { window, layout } => {
+                if let Ok(cell) = layout::parse(layout) {
+                    let mut windows = self.gateway.windows.lock();
+                    windows.layouts.insert(window, cell);
+                    windows.requested.clear();
+                    drop(windows);
+                    self.sync();
+                }
+            }
+            Notification::WindowAdd { .. } | Notification::SessionChanged => self.list_windows(),
+            Notification::WindowClose { window } => {
+                let removed = self.gateway.windows.lock().layouts.remove(&window);
+                if removed.is_some() {
+                    self.sync();
+                }
+            }
+            Notification::SubscriptionChanged { name, pane, value } => {
+                if let Some(pane) = pane.and_then(|pane| self.panes.get(&pane)) {
+                    if name == CWD_SUBSCRIPTION {
+                        pane.set_cwd(value);
+                    }
+                }
+            }
+            Notification::Exit => return true,
+            Notification::Other => {}
+        }
+        false
+    }
+
+    fn reply(&mut self, reply: Reply, lines: Vec<String>, ok: bool) {
+        match reply {
+            Reply::Ignore => {}
+            Reply::Windows if ok => {
+                let layouts = lines
+                    .iter()
+                    .filter_map(|line| {
+                        let (window, layout) = line.split_once(' ')?;
+                        let window = protocol::parse_id(window, '@')?;
+                        Some((window, layout::parse(layout).ok()?))
+                    })
+                    .collect();
+                *self.gateway.windows.lock() = Windows {
+                    layouts,
+                    requested: HashMap::new(),
+                };
+                self.sync();
+            }
+            Reply::Windows => {}
+            Reply::Cursor(pane) => {
+                let cursor = lines.first().and_then(|line| {
+                    let (x, y) = line.split_once(' ')?;
+                    Some((x.parse().ok()?, y.parse().ok()?))
+                });
+                if let Some(cursor) = cursor.filter(|_| ok) {
+                    self.cursors.insert(pane, cursor);
+                }
+            }
+            Reply::Capture(pane) => {
+                let cursor = self.cursors.remove(&pane);
+                if let Some(pane) = self.panes.get(&pane) {
+                    pane.captured(if ok { &lines } else { &[] }, cursor);
+                }
+            }
+        }
+    }
+
+    fn list_windows(&self) {
+        let _ = self.gateway.command(
+            "list-windows -F '#{window_id} #{window_layout}'",
+            Reply::Windows,
+        );
+    }
+
+    /// Bring the pane sessions in line with the window layouts and show them.
+    fn sync(&mut self) {
+        let gateway = &self.session_id;
+        let session_id = |pane: u32| pane_session_id(gateway, pane);
+        let (panes, tree) = {
+            let windows = self.gateway.windows.lock();
+            let panes: Vec<u32> = windows
+                .layouts
+                .values()
+                .flat_map(LayoutCell::panes)
+                .collect();
+            let tiles = windows
+                .layouts
+                .iter()
+                .map(|(window, cell)| (*window, cell.to_tile(&session_id, 0)))
+                .collect();
+            (panes, stack_windows(gateway, tiles))
+        };
+
+        let manager = self.app.state::<PtyManager>();
+        for &pane in &panes {
+            if self.panes.contains_key(&pane) {
+                continue;
+            }
+            let tmux_pane = Arc::new(TmuxPane::new(
+                self.app.clone(),
+                session_id(pane),
+                pane,
+                self.gateway.clone(),
+            ));
+            manager.insert_session(session_id(pane), tmux_pane.clone());
+            tmux_pane.capture(CAPTURE_HISTORY);
+            self.panes.insert(pane, tmux_pane);
+        }
+        self.panes.retain(|pane, tmux_pane| {
+            let keep = panes.contains(pane);
+            if !keep {
+                tmux_pane.close();
+            }
+            keep
+        });
+        emit(&self.app, &self.session_id, tree);
+    }
+}
+
+/// Show the gateway's tmux panes as `layout`, or the gateway itself for `None`.
+fn emit(app: &AppHandle, gateway: &str, layout: Option<TileNode>) {
+    let payload = TmuxLayout {
+        gateway_session_id: gateway.to_string(),
+        layout,
+    };
+    let _ = app.emit(types::LAYOUT_EVENT, &payload);
+}
+
+/// The session id a tmux pane is shown under.
+fn pane_session_id(gateway: &str, pane: u32) -> String {
+    format!("{gateway}-tmux-{pane}")
+}
+
+/// One tree for all of tmux's windows, stacked with equal shares.
+fn stack_windows(gateway: &str, tiles: Vec<(u32, TileNode)>) -> Option<TileNode> {
+    let mut tiles = tiles.into_iter().rev();
+    let (_, mut node) = tiles.next()?;
+    for (count, (window, tile)) in (2..).zip(tiles) {
+        node = TileNode::Split {
+            id: format!("{gateway}-tmux-window-{window}"),
+            direction: SplitDirection::Horizontal,
+            ratio: 1.0 / f64::from(count),
+            children: Box::new([tile, node]),
+        };
+    }
+    Some(node)
+}
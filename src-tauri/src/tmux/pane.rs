@@ -0,0 +1,197 @@
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Emitter};
+
+use super::Gateway;
+use crate::pty::backend::SessionBackend;
+use crate::pty::history::OutputHistory;
+use crate::pty::output::{self, FlowControl, OutputStream, OutputSwitch};
+use crate::pty::types::{PtyExit, PtyTeardown, TeardownOutcome};
+
+/// Output kept for `pty_attach`, as for local sessions.
+const HISTORY_CAPACITY: usize = 1024 * 1024;
+
+/// A pane of an attached tmux server, shown as a session of its own. Output
+/// comes from the gateway's `%output` notifications; input, resizes and
+/// closing go back as tmux commands.
+pub struct TmuxPane {
+    app: AppHandle,
+    session_id: String,
+    pane: u32,
+    gateway: Arc<Gateway>,
+    started: Instant,
+    flow: Arc<FlowControl>,
+    switch: OutputSwitch,
+    state: Mutex<PaneState>,
+    /// `pane_current_path`, as tmux last reported it.
+    cwd: Mutex<String>,
+}
+
+struct PaneState {
+    history: OutputHistory,
+    /// `None` once the pane is gone.
+    output: Option<OutputStream>,
+    /// `capture-pane` replies still to come. Until the last one has filled
+    /// in the screen, output is already part of what it will return.
+    captures: usize,
+    /// tmux has been told to stop sending the pane's output until the
+    /// frontend catches up.
+    held: bool,
+}
+
+impl TmuxPane {
+    pub(super) fn new(
+        app: AppHandle,
+        session_id: String,
+        pane: u32,
+        gateway: Arc<Gateway>,
+    ) -> Self {
+        let flow = Arc::new(FlowControl::default());
+        let output = OutputStream::new(output::unattached(), flow.clone());
+        TmuxPane {
+            app,
+            session_id,
+            pane,
+            gateway,
+            started: Instant::now(),
+            flow,
+            switch: output.switch(),
+            state: Mutex::new(PaneState {
+                history: OutputHistory::new(HISTORY_CAPACITY),
+                output: Some(output),
+                captures: 0,
+                held: false,
+            }),
+            cwd: Mutex::new(String::new()),
+        }
+    }
+
+    pub(super) fn set_cwd(&self, cwd: &str) {
+        *self.cwd.lock() = cwd.to_string();
+    }
+
+    /// Output from `%output`. Past the high watermark tmux stops sending it
+    /// until the frontend catches up, and the screen is then redrawn.
+    pub(super) fn output(&self, data: &[u8]) {
+        let mut state = self.state.lock();
+        if state.captures == 0 {
+            push(&mut state, data);
+        }
+        if !state.held && state.output.is_some() && self.flow.over_capacity() {
+            state.held = true;
+            drop(state);
+            let _ = self.gateway.set_pane_output(self.pane, false);
+        }
+    }
+
+    /// Have tmux send the pane's output again once the frontend has caught
+    /// up, and redraw the screen it missed meanwhile.
+    fn release_if_caught_up(&self) {
+        let mut state = self.state.lock();
+        if state.held && self.flow.caught_up() {
+            state.held = false;
+            drop(state);
+            let _ = self.gateway.set_pane_output(self.pane, true);
+            self.capture(0);
+        }
+    }
+
+    /// Ask tmux for the pane's screen and `history` lines above it, dropping
+    /// `%output` until it arrives.
+    pub(super) fn capture(&self, history: usize) {
+        self.state.lock().captures += 1;
+        if self.gateway.capture_pane(self.pane, history).is_err() {
+            self.state.lock().captures -= 1;
+        }
+    }
+
+    /// The pane's screen and history from `capture-pane`, drawn over whatever
+    /// was shown, with the cursor put back where tmux has it.
+    pub(super) fn captured(&self, lines: &[String], cursor: Option<(u16, u16)>) {
+        let mut data = b"\x1b[H\x1b[2J".to_vec();
+        data.extend_from_slice(lines.join("\r\n").as_bytes());
+        data.extend_from_slice(b"\x1b[0m");
+        if let Some((x, y)) = cursor {
+            data.extend_from_slice(format!("\x1b[{};{}H", y + 1, x + 1).as_bytes());
+        }
+        let mut state = self.state.lock();
+        push(&mut state, &data);
+        state.captures = state.captures.saturating_sub(1);
+    }
+
+    /// The pane is gone from tmux, or the gateway left control mode.
+    pub(super) fn close(&self) {
+        // Dropping the stream flushes it before the exit is announced
+        let Some(output) = self.state.lock().output.take() else {
+            return;
+        };
+        drop(output);
+        let exit = PtyExit {
+            exit_code: None,
+            signal: None,
+            signal_name: None,
+            runtime_ms: self.started.elapsed().as_millis() as u64,
+            error: None,
+        };
+        let _ = self
+            .app
+            .emit(&format!("pty-exit-{}", self.session_id), exit);
+    }
+
+    fn is_closed(&self) -> bool {
+        self.state.lock().output.is_none()
+    }
+}
+
+fn push(state: &mut PaneState, data: &[u8]) {
+    state.history.push(data);
+    if let Some(output) = &state.output {
+        output.push(data);
+    }
+}
+
+impl SessionBackend for TmuxPane {
+    fn write(&self, data: &[u8]) -> Result<(), String> {
+        self.gateway.send_keys(self.pane, data)
+    }
+
+    fn ack(&self, bytes: usize) {
+        self.flow.ack(bytes);
+        self.release_if_caught_up();
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<(), String> {
+        self.gateway.resize_pane(self.pane, cols, rows)
+    }
+
+    /// Kill the pane in tmux, unless it's already gone.
+    fn shutdown(&self, _grace: Duration) -> PtyTeardown {
+        let started = Instant::now();
+        if !self.is_closed() {
+            let _ = self.gateway.kill_pane(self.pane);
+        }
+        self.close();
+        PtyTeardown {
+            outcome: TeardownOutcome::Closed,
+            exit: None,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// The pane's directory as tmux last reported it, or "" before it has.
+    fn get_cwd(&self) -> String {
+        self.cwd.lock().clone()
+    }
+
+    fn attach(&self, on_output: Channel) -> Result<(), String> {
+        let state = self.state.lock();
+        let backlog = state.history.tail_from_line(HISTORY_CAPACITY);
+        // Still under the lock, so no output can slip in between
+        self.switch.attach(on_output, backlog);
+        drop(state);
+        self.release_if_caught_up();
+        Ok(())
+    }
+}
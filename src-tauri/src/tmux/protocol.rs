@@ -0,0 +1,303 @@
+/// What `tmux -CC` prints when it enters control mode: the start of a DCS
+/// sequence that lasts until control mode ends with `%exit` and ST.
+pub const DCS_START: &[u8] = b"\x1bP1000p";
+
+/// A line tmux sent outside a command's `%begin` / `%end` block.
+#[derive(Debug, PartialEq)]
+pub enum Notification<'a> {
+    /// A command's reply follows, up to `%end` or `%error`. `ours` is set for
+    /// commands this client sent, as opposed to the one that started tmux.
+    Begin {
+        ours: bool,
+    },
+    Output {
+        pane: u32,
+        data: Vec<u8>,
+    },
+    LayoutChange {
+        window: u32,
+        layout: &'a str,
+    },
+    WindowAdd {
+        window: u32,
+    },
+    WindowClose {
+        window: u32,
+    },
+    /// The client switched to another tmux session, with its own windows.
+    SessionChanged,
+    /// A `refresh-client -B` format changed; `pane` is set for pane formats.
+    SubscriptionChanged {
+        name: &'a str,
+        pane: Option<u32>,
+        value: &'a str,
+    },
+    Exit,
+    /// Anything this client doesn't act on (`%window-renamed`, `%pause`, ...).
+    Other,
+}
+
+/// Parse a notification line, without its line ending.
+pub fn parse_notification(line: &[u8]) -> Notification<'_> {
+    let (name, rest) = match line.iter().position(|&b| b == b' ') {
+        Some(i) => (&line[..i], &line[i + 1..]),
+        None => (line, &[][..]),
+    };
+    match name {
+        b"%begin" => Notification::Begin {
+            // %begin <time> <command number> <flags>
+            ours: field(rest, 2).is_some_and(|flags| flags == "1"),
+        },
+        b"%output" => {
+            let Some(space) = rest.iter().position(|&b| b == b' ') else {
+                return Notification::Other;
+            };
+            match std::str::from_utf8(&rest[..space])
+                .ok()
+                .and_then(|id| parse_id(id, '%'))
+            {
+                Some(pane) => Notification::Output {
+                    pane,
+                    data: unescape(&rest[space + 1..]),
+                },
+                None => Notification::Other,
+            }
+        }
+        b"%layout-change" => {
+            // %layout-change @<window> <layout> <visible layout> <flags>
+            match (
+                field(rest, 0).and_then(|id| parse_id(id, '@')),
+                field(rest, 1),
+            ) {
+                (Some(window), Some(layout)) => Notification::LayoutChange { window, layout },
+                _ => Notification::Other,
+            }
+        }
+        b"%window-add" => match field(rest, 0).and_then(|id| parse_id(id, '@')) {
+            Some(window) => Notification::WindowAdd { window },
+            None => Notification::Other,
+        },
+        b"%window-close" | b"%unlinked-window-close" => {
+            match field(rest, 0).and_then(|id| parse_id(id, '@')) {
+                Some(window) => Notification::WindowClose { window },
+                None => Notification::Other,
+            }
+        }
+        b"%session-changed" => Notification::SessionChanged,
+        b"%subscription-changed" => {
+            // %subscription-changed <name> $<session> @<window> <index> %<pane> ... : <value>
+            let Some((fields, value)) = std::str::from_utf8(rest)
+                .ok()
+                .and_then(|rest| rest.split_once(" : "))
+            else {
+                return Notification::Other;
+            };
+            let mut fields = fields.split(' ');
+            match fields.next() {
+                Some(name) if !name.is_empty() => Notification::SubscriptionChanged {
+                    name,
+                    pane: fields.nth(3).and_then(|id| parse_id(id, '%')),
+                    value,
+                },
+                _ => Notification::Other,
+            }
+        }
+        b"%exit" => Notification::Exit,
+        _ => Notification::Other,
+    }
+}
+
+/// Whether `line` ends the reply block that's open.
+pub fn is_block_end(line: &[u8]) -> bool {
+    line.starts_with(b"%end ") || line.starts_with(b"%error ")
+}
+
+/// The `index`th space-separated field of `rest`.
+fn field(rest: &[u8], index: usize) -> Option<&str> {
+    std::str::from_utf8(rest).ok()?.split(' ').nth(index)
+}
+
+/// `%12` → 12 for panes, `@3` → 3 for windows.
+pub fn parse_id(id: &str, sigil: char) -> Option<u32> {
+    id.strip_prefix(sigil)?.parse().ok()
+}
+
+/// Undo `%output` escaping: tmux writes control characters and backslashes
+/// as `\ooo` octal escapes and everything else as is.
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let octal = data
+            .get(i + 1..i + 4)
+            .filter(|digits| data[i] == b'\\' && digits.iter().all(|d| (b'0'..=b'7').contains(d)));
+        match octal {
+            Some(digits) => {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |value, d| value * 8 + u32::from(d - b'0'));
+                out.push(value as u8);
+                i += 4;
+            }
+            None => {
+                out.push(data[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn begin_is_ours_only_with_flags_1() {
+        assert_eq!(
+            parse_notification(b"%begin 1700000000 42 1"),
+            Notification::Begin { ours: true }
+        );
+        assert_eq!(
+            parse_notification(b"%begin 1700000000 42 0"),
+            Notification::Begin { ours: false }
+        );
+        assert_eq!(
+            parse_notification(b"%begin"),
+            Notification::Begin { ours: false }
+        );
+    }
+
+    #[test]
+    fn output_is_unescaped() {
+        assert_eq!(
+            parse_notification(b"%output %3 ls\\015\\012a b\\134"),
+            Notification::Output {
+                pane: 3,
+                data: b"ls\r\na b\\".to_vec(),
+            }
+        );
+        assert_eq!(
+            parse_notification(b"%output %3 "),
+            Notification::Output {
+                pane: 3,
+                data: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn malformed_output_is_other() {
+        assert_eq!(parse_notification(b"%output %3"), Notification::Other);
+        assert_eq!(parse_notification(b"%output @3 hi"), Notification::Other);
+        assert_eq!(parse_notification(b"%output %x hi"), Notification::Other);
+    }
+
+    #[test]
+    fn window_notifications() {
+        assert_eq!(
+            parse_notification(b"%layout-change @1 b25f,80x24,0,0,3 b25f,80x24,0,0,3 *"),
+            Notification::LayoutChange {
+                window: 1,
+                layout: "b25f,80x24,0,0,3",
+            }
+        );
+        assert_eq!(
+            parse_notification(b"%layout-change @1"),
+            Notification::Other
+        );
+        assert_eq!(
+            parse_notification(b"%window-add @4"),
+            Notification::WindowAdd { window: 4 }
+        );
+        assert_eq!(
+            parse_notification(b"%window-close @4"),
+            Notification::WindowClose { window: 4 }
+        );
+        assert_eq!(
+            parse_notification(b"%unlinked-window-close @5"),
+            Notification::WindowClose { window: 5 }
+        );
+        assert_eq!(parse_notification(b"%window-add 4"), Notification::Other);
+    }
+
+    #[test]
+    fn session_and_exit_notifications() {
+        assert_eq!(
+            parse_notification(b"%session-changed $1 main"),
+            Notification::SessionChanged
+        );
+        assert_eq!(parse_notification(b"%exit"), Notification::Exit);
+        assert_eq!(parse_notification(b"%exit detached"), Notification::Exit);
+        assert_eq!(
+            parse_notification(b"%window-renamed @1 vim"),
+            Notification::Other
+        );
+        assert_eq!(parse_notification(b"%pause %1"), Notification::Other);
+        assert_eq!(parse_notification(b""), Notification::Other);
+    }
+
+    #[test]
+    fn subscription_changes() {
+        assert_eq!(
+            parse_notification(b"%subscription-changed cwd $1 @2 0 %3 : /home/me/a : b"),
+            Notification::SubscriptionChanged {
+                name: "cwd",
+                pane: Some(3),
+                value: "/home/me/a : b",
+            }
+        );
+        assert_eq!(
+            parse_notification(b"%subscription-changed name $1 - - - : main"),
+            Notification::SubscriptionChanged {
+                name: "name",
+                pane: None,
+                value: "main",
+            }
+        );
+        assert_eq!(
+            parse_notification(b"%subscription-changed cwd $1 @2 0 %3 : "),
+            Notification::SubscriptionChanged {
+                name: "cwd",
+                pane: Some(3),
+                value: "",
+            }
+        );
+        assert_eq!(
+            parse_notification(b"%subscription-changed cwd $1 @2 0 %3"),
+            Notification::Other
+        );
+    }
+
+    #[test]
+    fn block_ends() {
+        assert!(is_block_end(b"%end 1700000000 42 1"));
+        assert!(is_block_end(b"%error 1700000000 42 1"));
+        assert!(!is_block_end(b"%ending"));
+        assert!(!is_block_end(b"%output %1 %end "));
+    }
+
+    #[test]
+    fn ids_need_their_sigil() {
+        assert_eq!(parse_id("%12", '%'), Some(12));
+        assert_eq!(parse_id("@3", '@'), Some(3));
+        assert_eq!(parse_id("@3", '%'), None);
+        assert_eq!(parse_id("%", '%'), None);
+    }
+
+    #[test]
+    fn unescape_octal() {
+        assert_eq!(unescape(b"\\033[1mbold\\033[0m"), b"\x1b[1mbold\x1b[0m");
+        assert_eq!(unescape(b"\\000\\177\\377"), b"\x00\x7f\xff");
+        assert_eq!(unescape(b"C:\\134tmp"), b"C:\\tmp");
+        assert_eq!(unescape(b"plain text"), b"plain text");
+    }
+
+    #[test]
+    fn unescape_leaves_incomplete_escapes() {
+        assert_eq!(unescape(b"\\"), b"\\");
+        assert_eq!(unescape(b"a\\03"), b"a\\03");
+        assert_eq!(unescape(b"\\089"), b"\\089");
+        assert_eq!(unescape(b"\\\\012"), b"\\\n");
+    }
+}
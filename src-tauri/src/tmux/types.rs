@@ -0,0 +1,16 @@
+use serde::Serialize;
+
+use crate::workspace::types::TileNode;
+
+/// Emitted whenever a gateway's tmux windows change.
+pub const LAYOUT_EVENT: &str = "tmux-layout";
+
+/// Payload of `tmux-layout`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TmuxLayout {
+    /// The session running `tmux -CC`.
+    pub gateway_session_id: String,
+    /// Every window's panes, stacked; `None` once control mode has ended and
+    /// the gateway session should be shown again.
+    pub layout: Option<TileNode>,
+}
@@ -0,0 +1,46 @@
+use tauri::{AppHandle, Manager};
+
+use super::layout;
+use super::types::{LayoutOpen, TileNode, Workspace};
+use super::WorkspaceState;
+use crate::pty::manager::PtyManager;
+
+/// Record the current tile layout and save it with the panes' profiles. It's
+/// saved again on quit, with the panes' cwd and scrollback.
+#[tauri::command]
+pub async fn workspace_set_layout(app: AppHandle, layout: TileNode) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<WorkspaceState>();
+        state.set_layout(layout);
+        state.save_layout(&app.state::<PtyManager>())
+    })
+    .await
+    .map_err(|e| format!("Workspace save task failed: {e}"))?
+}
+
+/// The workspace saved on the last quit, to recreate at launch.
+#[tauri::command]
+pub fn workspace_restore() -> Result<Option<Workspace>, String> {
+    super::load()
+}
+
+/// Start the sessions described by the nearest `.terminal-plus.toml` in `cwd`
+/// or its parents, and return the tile tree to show them in. The panes then
+/// connect with `pty_attach`. A file that isn't trusted yet starts nothing;
+/// `trust` is the hash it was shown to the user with. Runs off the main
+/// thread since it waits for the shells' prompts.
+#[tauri::command]
+pub async fn workspace_open_layout(
+    app: AppHandle,
+    cwd: String,
+    cols: u16,
+    rows: u16,
+    trust: Option<String>,
+) -> Result<LayoutOpen, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let manager = app.state::<PtyManager>();
+        layout::open(&app, &manager, &cwd, cols, rows, trust.as_deref())
+    })
+    .await
+    .map_err(|e| format!("Layout task failed: {e}"))?
+}
@@ -0,0 +1,520 @@
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use super::trust;
+use super::types::{LayoutOpen, SplitDirection, TileNode};
+use crate::pty::manager::PtyManager;
+use crate::pty::output;
+use crate::pty::profile::{self, ShellProfile, DEFAULT_PROFILE_ID};
+use crate::pty::scrollback::ScrollbackConfig;
+use crate::pty::session::{ExitPolicy, SpawnOptions};
+use crate::tasks::discover::find_up;
+
+/// The layout file a project checks in.
+pub const LAYOUT_FILE: &str = ".terminal-plus.toml";
+
+/// How long to wait for the panes' shells to draw a prompt before typing
+/// their commands anyway (e.g. for shells without integration).
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `.terminal-plus.toml`: environment for every pane, and the split tree.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutFile {
+    #[serde(default)]
+    env: HashMap<String, String>,
+    layout: LayoutNode,
+}
+
+/// A pane, or a split of two or more nodes when `split` is set. `cwd`,
+/// `profile` and `env` set on a split apply to everything inside it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LayoutNode {
+    split: Option<SplitDirection>,
+    /// Size of the first pane, 0..1; only for splits of exactly two.
+    ratio: Option<f64>,
+    panes: Vec<LayoutNode>,
+    /// Relative to the enclosing node's directory (the file's, at the top).
+    cwd: Option<String>,
+    profile: Option<String>,
+    env: HashMap<String, String>,
+    /// Typed into the pane's shell once it starts.
+    commands: Vec<String>,
+}
+
+/// What the enclosing nodes set.
+#[derive(Clone)]
+struct Inherited {
+    cwd: PathBuf,
+    profile: Option<String>,
+    env: HashMap<String, String>,
+}
+
+/// A validated pane, ready to spawn.
+struct PanePlan {
+    session_id: String,
+    cwd: String,
+    profile: ShellProfile,
+    commands: Vec<String>,
+}
+
+/// Find the nearest layout file in `cwd` or its parents, validate it, start
+/// a session for each pane and return the tile tree to show them in. Nothing
+/// is started unless the whole file is valid, and a file that sets commands
+/// or environment must be trusted first: either approved before with the
+/// same contents, or with `trust` set to the hash the user was shown. Each
+/// pane's commands are typed once its shell has drawn a prompt. The
+/// sessions' output is held for the frontend to collect with `pty_attach`.
+pub fn open(
+    app: &AppHandle,
+    manager: &PtyManager,
+    cwd: &str,
+    cols: u16,
+    rows: u16,
+    trust: Option<&str>,
+) -> Result<LayoutOpen, String> {
+    let start = Path::new(cwd);
+    if !start.is_dir() {
+        return Err(format!("Not a directory: {cwd}"));
+    }
+    let path = find_up(start, &[LAYOUT_FILE])
+        .ok_or_else(|| format!("No {LAYOUT_FILE} in {cwd} or its parents"))?;
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let file: LayoutFile = toml::from_str(&contents)
+        .map_err(|e| format!("Invalid layout file {}: {e}", path.display()))?;
+
+    let hash = trust::hash(&contents);
+    let commands = commands(&file.layout);
+    let env = env(&file);
+    if (!commands.is_empty() || !env.is_empty()) && !trust::is_trusted(&path, &hash)? {
+        if trust != Some(hash.as_str()) {
+            return Ok(LayoutOpen::Untrusted {
+                path: path.to_string_lossy().into_owned(),
+                hash,
+                commands,
+                env,
+            });
+        }
+        trust::trust(&path, &hash)?;
+    }
+
+    let profiles = profile::load_profiles()?;
+    let root = Inherited {
+        cwd: path.parent().unwrap_or(Path::new("/")).to_path_buf(),
+        profile: None,
+        env: file.env,
+    };
+    let mut panes = Vec::new();
+    let layout = plan(&file.layout, &root, &profiles, "layout", &mut panes)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let scrollback = ScrollbackConfig::load()?;
+    let mut started: Vec<&str> = Vec::new();
+    let stop = |started: &[&str]| {
+        for session_id in started {
+            manager.destroy_session(session_id, Duration::ZERO);
+        }
+    };
+    for pane in &panes {
+        let options = SpawnOptions {
+            cols,
+            rows,
+            cwd: Some(pane.cwd.clone()),
+            profile: pane.profile.clone(),
+            scrollback: scrollback.clone(),
+            on_exit: ExitPolicy::default(),
+        };
+        if let Err(e) = manager.create_session(
+            app.clone(),
+            pane.session_id.clone(),
+            options,
+            output::unattached(),
+        ) {
+            stop(&started);
+            return Err(e);
+        }
+        started.push(&pane.session_id);
+    }
+    // Typed any earlier, commands could be read by the shell's rc files
+    let deadline = Instant::now() + PROMPT_TIMEOUT;
+    for pane in panes.iter().filter(|pane| !pane.commands.is_empty()) {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let typed = manager
+            .wait_for_prompt(&pane.session_id, timeout)
+            .and_then(|_| {
+                pane.commands.iter().try_for_each(|command| {
+                    manager.write_to_session(&pane.session_id, format!("{command}\r").as_bytes())
+                })
+            });
+        if let Err(e) = typed {
+            stop(&started);
+            return Err(e);
+        }
+    }
+    Ok(LayoutOpen::Opened { layout })
+}
+
+/// Every pane's commands, in file order.
+fn commands(node: &LayoutNode) -> Vec<String> {
+    let mut all = node.commands.clone();
+    all.extend(node.panes.iter().flat_map(commands));
+    all
+}
+
+/// Every `KEY=value` the file sets, at the top or on any node.
+fn env(file: &LayoutFile) -> Vec<String> {
+    fn collect(node: &LayoutNode, env: &mut BTreeSet<String>) {
+        env.extend(node.env.iter().map(|(k, v)| format!("{k}={v}")));
+        for child in &node.panes {
+            collect(child, env);
+        }
+    }
+    let mut env: BTreeSet<String> = file.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    collect(&file.layout, &mut env);
+    env.into_iter().collect()
+}
+
+/// Validate `node` and the nodes under it, adding a plan for each pane.
+/// `at` names the node in errors, e.g. `layout.panes[1]`.
+fn plan(
+    node: &LayoutNode,
+    inherited: &Inherited,
+    profiles: &[ShellProfile],
+    at: &str,
+    panes: &mut Vec<PanePlan>,
+) -> Result<TileNode, String> {
+    let mut scope = inherited.clone();
+    if let Some(cwd) = &node.cwd {
+        scope.cwd = resolve_dir(&inherited.cwd, cwd).map_err(|e| format!("{at}.cwd: {e}"))?;
+    }
+    if let Some(id) = &node.profile {
+        if !profiles.iter().any(|p| &p.id == id) {
+            return Err(format!("{at}.profile: no profile named {id}"));
+        }
+        scope.profile = Some(id.clone());
+    }
+    scope
+        .env
+        .extend(node.env.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+    let Some(direction) = node.split else {
+        if !node.panes.is_empty() {
+            return Err(format!("{at}: panes need a split direction"));
+        }
+        if node.ratio.is_some() {
+            return Err(format!("{at}.ratio: only splits have a ratio"));
+        }
+        return Ok(plan_pane(node, scope, profiles, panes));
+    };
+
+    if !node.commands.is_empty() {
+        return Err(format!("{at}.commands: only panes run commands"));
+    }
+    if node.panes.len() < 2 {
+        return Err(format!("{at}.panes: a split needs at least two panes"));
+    }
+    let ratio = match node.ratio {
+        Some(_) if node.panes.len() != 2 => {
+            return Err(format!("{at}.ratio: only splits of two panes take a ratio"));
+        }
+        Some(ratio) if !(ratio > 0.0 && ratio < 1.0) => {
+            return Err(format!("{at}.ratio: {ratio} is not between 0 and 1"));
+        }
+        Some(ratio) => ratio,
+        None => 1.0 / node.panes.len() as f64,
+    };
+    let children = node
+        .panes
+        .iter()
+        .enumerate()
+        .map(|(i, child)| plan(child, &scope, profiles, &format!("{at}.panes[{i}]"), panes))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(even_splits(direction, ratio, children))
+}
+
+fn plan_pane(
+    node: &LayoutNode,
+    scope: Inherited,
+    profiles: &[ShellProfile],
+    panes: &mut Vec<PanePlan>,
+) -> TileNode {
+    let id = scope.profile.as_deref().unwrap_or(DEFAULT_PROFILE_ID);
+    let mut profile = profiles
+        .iter()
+        .find(|p| p.id == id)
+        .cloned()
+        .unwrap_or_else(ShellProfile::builtin_default);
+    profile.env.extend(scope.env);
+
+    let session_id = short_id();
+    panes.push(PanePlan {
+        session_id: session_id.clone(),
+        cwd: scope.cwd.to_string_lossy().into_owned(),
+        profile,
+        commands: node.commands.clone(),
+    });
+    TileNode::Leaf {
+        id: short_id(),
+        session_id,
+    }
+}
+
+/// Nest `children` into the frontend's binary splits: the first child takes
+/// `ratio`, and the rest share what's left evenly.
+fn even_splits(direction: SplitDirection, ratio: f64, mut children: Vec<TileNode>) -> TileNode {
+    let first = children.remove(0);
+    let rest = if children.len() == 1 {
+        children.remove(0)
+    } else {
+        let ratio = 1.0 / children.len() as f64;
+        even_splits(direction, ratio, children)
+    };
+    TileNode::Split {
+        id: short_id(),
+        direction,
+        ratio,
+        children: Box::new([first, rest]),
+    }
+}
+
+/// `path` relative to `base`, with `~` for the home directory. Must exist.
+fn resolve_dir(base: &Path, path: &str) -> Result<PathBuf, String> {
+    let home_relative = path
+        .strip_prefix('~')
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'));
+    let resolved = match home_relative {
+        Some(rest) => {
+            let home = std::env::var("HOME").unwrap_or_else(|_| "/".to_string());
+            PathBuf::from(home).join(rest.trim_start_matches('/'))
+        }
+        None => base.join(path),
+    };
+    if resolved.is_dir() {
+        Ok(resolved)
+    } else {
+        Err(format!("{} is not a directory", resolved.display()))
+    }
+}
+
+/// An id in the style of the frontend's `nanoid(8)`.
+fn short_id() -> String {
+    uuid::Uuid::new_v4().simple().to_string()[..8].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory with an `app` subdirectory, removed on drop.
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("terminal-plus-layout-{}", short_id()));
+            std::fs::create_dir_all(dir.join("app")).unwrap();
+            Dir(dir)
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn profiles() -> Vec<ShellProfile> {
+        let bash = ShellProfile {
+            id: "bash".to_string(),
+            name: "Bash".to_string(),
+            program: Some("/bin/bash".to_string()),
+            ..ShellProfile::builtin_default()
+        };
+        vec![ShellProfile::builtin_default(), bash]
+    }
+
+    fn plan_file(dir: &Dir, toml: &str) -> Result<(TileNode, Vec<PanePlan>), String> {
+        let file: LayoutFile = toml::from_str(toml).map_err(|e| e.to_string())?;
+        let root = Inherited {
+            cwd: dir.0.clone(),
+            profile: None,
+            env: file.env,
+        };
+        let mut panes = Vec::new();
+        let layout = plan(&file.layout, &root, &profiles(), "layout", &mut panes)?;
+        Ok((layout, panes))
+    }
+
+    fn plan_error(toml: &str) -> String {
+        match plan_file(&Dir::new(), toml) {
+            Ok(_) => panic!("planned:\n{toml}"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn a_single_pane() {
+        let dir = Dir::new();
+        let (layout, panes) = plan_file(&dir, "[layout]\ncommands = [\"make\"]").unwrap();
+        let TileNode::Leaf { session_id, .. } = layout else {
+            panic!("expected a pane");
+        };
+        assert_eq!(panes.len(), 1);
+        assert_eq!(panes[0].session_id, session_id);
+        assert_eq!(panes[0].cwd, dir.0.to_string_lossy());
+        assert_eq!(panes[0].profile.id, DEFAULT_PROFILE_ID);
+        assert_eq!(panes[0].commands, ["make"]);
+    }
+
+    #[test]
+    fn splits_of_three_share_space_evenly() {
+        let (layout, panes) = plan_file(
+            &Dir::new(),
+            "[layout]\nsplit = \"vertical\"\npanes = [{}, {}, {}]",
+        )
+        .unwrap();
+        assert_eq!(panes.len(), 3);
+        let TileNode::Split {
+            direction: SplitDirection::Vertical,
+            ratio,
+            children,
+            ..
+        } = layout
+        else {
+            panic!("expected a split");
+        };
+        assert!((ratio - 1.0 / 3.0).abs() < 1e-9);
+        let [TileNode::Leaf { .. }, TileNode::Split { ratio, .. }] = *children else {
+            panic!("expected a pane and a split of the rest");
+        };
+        assert!((ratio - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn splits_pass_cwd_profile_and_env_down() {
+        let dir = Dir::new();
+        let toml = r#"
+            env = { A = "file", B = "file" }
+            [layout]
+            split = "horizontal"
+            ratio = 0.7
+            cwd = "app"
+            profile = "bash"
+            env = { B = "split" }
+            [[layout.panes]]
+            env = { C = "pane" }
+            [[layout.panes]]
+            cwd = ".."
+            profile = "default"
+        "#;
+        let (layout, panes) = plan_file(&dir, toml).unwrap();
+        let TileNode::Split { ratio, .. } = layout else {
+            panic!("expected a split");
+        };
+        assert!((ratio - 0.7).abs() < 1e-9);
+
+        assert_eq!(panes[0].cwd, dir.0.join("app").to_string_lossy());
+        assert_eq!(panes[0].profile.id, "bash");
+        let env = &panes[0].profile.env;
+        assert_eq!(env["A"], "file");
+        assert_eq!(env["B"], "split");
+        assert_eq!(env["C"], "pane");
+
+        assert_eq!(Path::new(&panes[1].cwd), dir.0.join("app/.."));
+        assert_eq!(panes[1].profile.id, DEFAULT_PROFILE_ID);
+        assert!(!panes[1].profile.env.contains_key("C"));
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        assert!(plan_error("[layout]\ncomand = [\"make\"]").contains("unknown field `comand`"));
+        assert!(plan_error("layouts = {}\n[layout]").contains("unknown field `layouts`"));
+        assert!(
+            plan_error("[layout]\nsplit = \"vertical\"\npanes = [{ cmd = \"ls\" }, {}]")
+                .contains("unknown field `cmd`")
+        );
+        assert!(
+            plan_error("[layout]\nsplit = \"diagonal\"\npanes = [{}, {}]")
+                .contains("unknown variant `diagonal`")
+        );
+    }
+
+    #[test]
+    fn rejects_bad_splits() {
+        assert_eq!(
+            plan_error("[layout]\nsplit = \"vertical\"\npanes = [{}]"),
+            "layout.panes: a split needs at least two panes"
+        );
+        assert_eq!(
+            plan_error("[layout]\npanes = [{}, {}]"),
+            "layout: panes need a split direction"
+        );
+        assert_eq!(
+            plan_error("[layout]\nsplit = \"vertical\"\npanes = [{}, {}]\ncommands = [\"ls\"]"),
+            "layout.commands: only panes run commands"
+        );
+        assert_eq!(
+            plan_error("[layout]\nsplit = \"vertical\"\npanes = [{}, { split = \"horizontal\" }]"),
+            "layout.panes[1].panes: a split needs at least two panes"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_ratios() {
+        assert_eq!(
+            plan_error("[layout]\nratio = 0.5"),
+            "layout.ratio: only splits have a ratio"
+        );
+        assert_eq!(
+            plan_error("[layout]\nsplit = \"vertical\"\nratio = 0.5\npanes = [{}, {}, {}]"),
+            "layout.ratio: only splits of two panes take a ratio"
+        );
+        for ratio in ["0.0", "1.0", "1.5", "-0.2", "nan"] {
+            assert_eq!(
+                plan_error(&format!(
+                    "[layout]\nsplit = \"vertical\"\nratio = {ratio}\npanes = [{{}}, {{}}]"
+                )),
+                format!(
+                    "layout.ratio: {} is not between 0 and 1",
+                    ratio.parse::<f64>().unwrap()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_profiles_and_missing_directories() {
+        assert_eq!(
+            plan_error("[layout]\nsplit = \"vertical\"\npanes = [{}, { profile = \"nope\" }]"),
+            "layout.panes[1].profile: no profile named nope"
+        );
+        let error = plan_error("[layout]\ncwd = \"missing\"");
+        assert!(error.starts_with("layout.cwd: "), "{error}");
+        assert!(error.ends_with("missing is not a directory"), "{error}");
+    }
+
+    #[test]
+    fn lists_what_a_file_runs() {
+        let toml = r#"
+            env = { A = "1" }
+            [layout]
+            split = "vertical"
+            env = { B = "2" }
+            [[layout.panes]]
+            commands = ["npm install", "npm run dev"]
+            [[layout.panes]]
+            env = { A = "3" }
+            commands = ["cargo run"]
+        "#;
+        let file: LayoutFile = toml::from_str(toml).unwrap();
+        assert_eq!(
+            commands(&file.layout),
+            ["npm install", "npm run dev", "cargo run"]
+        );
+        assert_eq!(env(&file), ["A=1", "A=3", "B=2"]);
+    }
+}
@@ -0,0 +1,177 @@
+//! Workspace restore: the tile layout and each pane's directory, profile and
+//! (optionally) recent output, saved on quit and recreated on the next launch.
+//! Projects can also describe a layout in a checked-in file (see [`layout`]).
+
+pub mod commands;
+pub mod layout;
+pub mod trust;
+pub mod types;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::pty::manager::PtyManager;
+use crate::pty::profile;
+use crate::pty::types::LineRange;
+use crate::settings::{self, terminal_plus_dir};
+use types::{SavedPane, TileNode, Workspace, WORKSPACE_VERSION};
+
+/// The `workspace` section of `~/.terminal-plus/settings.json`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceConfig {
+    /// Save the workspace on quit and recreate it on launch.
+    pub restore: bool,
+    /// Lines of each pane's output to save with it; 0 saves none.
+    pub scrollback_lines: usize,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        WorkspaceConfig {
+            restore: true,
+            scrollback_lines: 0,
+        }
+    }
+}
+
+impl WorkspaceConfig {
+    /// Load the config, using the defaults when the settings file doesn't exist.
+    pub fn load() -> Result<Self, String> {
+        settings::load_section("workspace")
+    }
+}
+
+fn workspace_path() -> PathBuf {
+    terminal_plus_dir().join("workspace.json")
+}
+
+/// The frontend's current layout, kept up to date so it can be saved on quit
+/// without a round trip to a window that may already be gone.
+#[derive(Default)]
+pub struct WorkspaceState {
+    layout: Mutex<Option<TileNode>>,
+    /// Held while writing, so an older save can't land after a newer one
+    saving: Mutex<()>,
+}
+
+impl WorkspaceState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_layout(&self, layout: TileNode) {
+        *self.layout.lock() = Some(layout);
+    }
+
+    /// Write the workspace file from the last layout and the panes' profiles,
+    /// leaving their cwd and scrollback to [`Self::save`] on quit.
+    pub fn save_layout(&self, manager: &PtyManager) -> Result<(), String> {
+        self.write(manager, false)
+    }
+
+    /// Write the workspace file from the last layout and the panes' live
+    /// state. Does nothing before the frontend has sent a layout.
+    pub fn save(&self, manager: &PtyManager) -> Result<(), String> {
+        self.write(manager, true)
+    }
+
+    fn write(&self, manager: &PtyManager, live: bool) -> Result<(), String> {
+        let config = WorkspaceConfig::load()?;
+        if !config.restore {
+            return Ok(());
+        }
+        let _saving = self.saving.lock();
+        let Some(layout) = self.layout.lock().clone() else {
+            return Ok(());
+        };
+
+        let profile_ids: Vec<String> = profile::load_profiles()
+            .map(|profiles| profiles.into_iter().map(|p| p.id).collect())
+            .unwrap_or_default();
+        let infos: HashMap<String, _> = manager
+            .list_sessions()
+            .into_iter()
+            .map(|info| (info.session_id.clone(), info))
+            .collect();
+
+        let mut panes = HashMap::new();
+        for session_id in layout.session_ids() {
+            // Replays and sessions that never started have nothing to restore
+            let Some(info) = infos.get(session_id) else {
+                continue;
+            };
+            // Command panes carry their program name as the profile id
+            let profile_id = Some(info.profile_id.clone()).filter(|id| profile_ids.contains(id));
+            let scrollback = if live && config.scrollback_lines > 0 {
+                recent_output(manager, session_id, config.scrollback_lines)
+            } else {
+                Vec::new()
+            };
+            panes.insert(
+                session_id.to_string(),
+                SavedPane {
+                    cwd: live.then(|| manager.get_session_cwd(session_id)).flatten(),
+                    profile_id,
+                    scrollback,
+                },
+            );
+        }
+
+        let workspace = Workspace {
+            version: WORKSPACE_VERSION,
+            layout,
+            panes,
+        };
+        write_atomically(&workspace)
+    }
+}
+
+/// The last `lines` lines of the session's screen model, styled, without the
+/// blank rows below the cursor.
+fn recent_output(manager: &PtyManager, session_id: &str, lines: usize) -> Vec<String> {
+    let range = LineRange {
+        start: 0,
+        end: usize::MAX,
+    };
+    let Ok(text) = manager.get_session_screen_text(session_id, Some(range), true) else {
+        return Vec::new();
+    };
+    let mut text = text.lines;
+    while text.last().is_some_and(|line| line.is_empty()) {
+        text.pop();
+    }
+    let skip = text.len().saturating_sub(lines);
+    text.split_off(skip)
+}
+
+fn write_atomically(workspace: &Workspace) -> Result<(), String> {
+    let path = workspace_path();
+    let json = serde_json::to_vec_pretty(workspace)
+        .map_err(|e| format!("Failed to serialize the workspace: {e}"))?;
+    std::fs::create_dir_all(terminal_plus_dir())
+        .map_err(|e| format!("Failed to create {}: {e}", terminal_plus_dir().display()))?;
+    // Write beside the real file and rename, so a crash mid-write can't leave it truncated
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {e}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// The workspace saved on the last quit, if any. A missing file, a file from
+/// another version, or restore being turned off all give `None`.
+pub fn load() -> Result<Option<Workspace>, String> {
+    if !WorkspaceConfig::load()?.restore {
+        return Ok(None);
+    }
+    let path = workspace_path();
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+    };
+    let workspace: Workspace = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid workspace file {}: {e}", path.display()))?;
+    Ok((workspace.version == WORKSPACE_VERSION).then_some(workspace))
+}
@@ -0,0 +1,50 @@
+//! Layout files the user has approved, by path and SHA-256 of the contents.
+//! A checked-in file can run commands, so a new or changed one is shown to
+//! the user before it's opened.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::settings::terminal_plus_dir;
+
+fn trust_path() -> PathBuf {
+    terminal_plus_dir().join("trusted-layouts.json")
+}
+
+/// Hex SHA-256 of a layout file's contents.
+pub fn hash(contents: &str) -> String {
+    format!("{:x}", Sha256::digest(contents.as_bytes()))
+}
+
+/// Whether `path` was approved with exactly this `hash`.
+pub fn is_trusted(path: &Path, hash: &str) -> Result<bool, String> {
+    let trusted = load()?;
+    Ok(trusted.get(path.to_string_lossy().as_ref()) == Some(&hash.to_string()))
+}
+
+/// Approve `path` with `hash`, replacing an approval of older contents.
+pub fn trust(path: &Path, hash: &str) -> Result<(), String> {
+    let mut trusted = load()?;
+    trusted.insert(path.to_string_lossy().into_owned(), hash.to_string());
+
+    let path = trust_path();
+    let json = serde_json::to_vec_pretty(&trusted)
+        .map_err(|e| format!("Failed to serialize the trusted layouts: {e}"))?;
+    let dir = terminal_plus_dir();
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("Failed to write {}: {e}", tmp.display()))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+fn load() -> Result<HashMap<String, String>, String> {
+    let path = trust_path();
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid trusted layouts file {}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+    }
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Bumped whenever the workspace file changes shape; older files are ignored.
+pub const WORKSPACE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// The frontend's tile tree (`src/types/tile.ts`), in the same JSON shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TileNode {
+    Split {
+        id: String,
+        direction: SplitDirection,
+        /// Size of the first child, 0..1.
+        ratio: f64,
+        children: Box<[TileNode; 2]>,
+    },
+    Leaf {
+        id: String,
+        #[serde(rename = "sessionId")]
+        session_id: String,
+    },
+}
+
+impl TileNode {
+    /// Session ids of the leaves, left to right.
+    pub fn session_ids(&self) -> Vec<&str> {
+        let mut ids = Vec::new();
+        self.collect_session_ids(&mut ids);
+        ids
+    }
+
+    fn collect_session_ids<'a>(&'a self, ids: &mut Vec<&'a str>) {
+        match self {
+            TileNode::Split { children, .. } => {
+                children[0].collect_session_ids(ids);
+                children[1].collect_session_ids(ids);
+            }
+            TileNode::Leaf { session_id, .. } => ids.push(session_id),
+        }
+    }
+}
+
+/// The result of opening a project layout file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum LayoutOpen {
+    /// The panes were started; show them in this tree.
+    Opened { layout: TileNode },
+    /// Nothing was started: the file is new or has changed since it was last
+    /// trusted. Open it again with `hash` to trust it.
+    Untrusted {
+        path: String,
+        hash: String,
+        /// Every pane's commands, in file order.
+        commands: Vec<String>,
+        /// `KEY=value` for every variable the file sets.
+        env: Vec<String>,
+    },
+}
+
+/// What a pane was doing when the app quit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPane {
+    /// The shell's working directory at quit.
+    pub cwd: Option<String>,
+    /// `None` for the default profile and for command panes, which come back as shells.
+    pub profile_id: Option<String>,
+    /// The last lines of output, with SGR sequences; empty unless enabled.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scrollback: Vec<String>,
+}
+
+/// `~/.terminal-plus/workspace.json`: the tile layout and its panes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    pub version: u32,
+    pub layout: TileNode,
+    /// Keyed by session id.
+    pub panes: HashMap<String, SavedPane>,
+}
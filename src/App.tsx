@@ -13,10 +13,13 @@ import { MarkdownPreview } from "./components/markdown/MarkdownPreview";
 import { SettingsOverlay } from "./components/settings/SettingsOverlay";
 import { ShortcutsOverlay } from "./components/shortcuts/ShortcutsOverlay";
 import { WelcomeScreen } from "./components/WelcomeScreen";
-import { registerKeybindings, type KeyAction } from "./lib/keybindings";
-import { findLeafById } from "./lib/tileTree";
-import { destroyPtySession } from "./components/terminal/useTerminal";
+import { registerKeybindings, shortcutLabel, type KeyAction } from "./lib/keybindings";
+import { collectLeaves, findLeafById } from "./lib/tileTree";
+import { destroyPtySession, markReattachable } from "./components/terminal/useTerminal";
 import { applyTheme } from "./lib/themeApplicator";
+import { terminalInstances } from "./lib/terminalRegistry";
+import { ptyGetCwd, workspaceOpenLayout } from "./lib/ipc";
+import type { TileLeaf } from "./types/tile";
 
 import "./styles/variables.css";
 import "./styles/terminal.css";
@@ -28,6 +31,46 @@ import "./styles/highlight.css";
 import "./styles/markdown.css";
 import "./App.css";
 
+/** Layout files listed in a pane (by leaf id), opened by asking again. */
+const pendingTrust = new Map<string, string>();
+
+/**
+ * Open the panes from the nearest .terminal-plus.toml above `leaf`'s shell's
+ * directory beside it. A file that isn't trusted yet has what it runs listed
+ * in the pane, and opens if the shortcut is pressed there again. Errors are
+ * printed in the pane.
+ */
+async function openProjectLayout(leaf: TileLeaf) {
+  const term = terminalInstances.get(leaf.sessionId)?.term;
+  const trust = pendingTrust.get(leaf.id);
+  pendingTrust.delete(leaf.id);
+  try {
+    const cwd = await ptyGetCwd(leaf.sessionId);
+    const opened = await workspaceOpenLayout(cwd, term?.cols ?? 80, term?.rows ?? 24, trust);
+    if (opened.status === "untrusted") {
+      pendingTrust.set(leaf.id, opened.hash);
+      const lines = [
+        ...opened.commands.map((command) => `  $ ${command}`),
+        ...opened.env.map((variable) => `  env ${variable}`),
+      ];
+      const shortcut = shortcutLabel("open-layout");
+      term?.write(
+        `\r\n\x1b[33m[${opened.path} runs:]\x1b[0m\r\n${lines.join("\r\n")}\r\n` +
+          `\x1b[33m[Press ${shortcut} again to trust it and open it]\x1b[0m\r\n`
+      );
+      return;
+    }
+    const sessionIds = collectLeaves(opened.layout).map((l) => l.sessionId);
+    // The backend already started these sessions; the panes attach to them
+    markReattachable(sessionIds);
+    if (!useTileStore.getState().splitWithLayout(leaf.id, opened.layout)) {
+      for (const id of sessionIds) destroyPtySession(id).catch(() => {});
+    }
+  } catch (e) {
+    term?.write(`\r\n\x1b[31m[${e}]\x1b[0m\r\n`);
+  }
+}
+
 function App() {
   const root = useTileStore((s) => s.root);
   const split = useTileStore((s) => s.split);
@@ -76,6 +119,11 @@ function App() {
           if (leaf) toggleSearch(leaf.sessionId);
           break;
         }
+        case "open-layout": {
+          const leaf = findLeafById(useTileStore.getState().root, focusedLeafId);
+          if (leaf) openProjectLayout(leaf);
+          break;
+        }
       }
     },
     [split, closePane, focusNext, focusPrev, focusedLeafId, gitToggle, toggleSearch]
@@ -1,7 +1,7 @@
 import { useEffect, useCallback, useRef, useState, useMemo } from "react";
 import { useGitStore } from "../../stores/gitStore";
 import { useTileStore } from "../../stores/tileStore";
-import { gitLog, gitIsRepo, ptyGetCwd } from "../../lib/ipc";
+import { gitLog, gitIsRepo, ptyGetCwd, onPtyCwdChanged } from "../../lib/ipc";
 const openLocalChanges = () => useGitStore.getState().openLocalChanges();
 import { layoutGitGraph } from "../../lib/gitGraphLayout";
 import { findLeafById } from "../../lib/tileTree";
@@ -9,6 +9,9 @@ import { GitGraph, ROW_HEIGHT } from "./GitGraph";
 import { GitCommitRow } from "./GitCommitRow";
 import type { GitCommit } from "../../types/git";
 
+// Fallback for shells that don't report their cwd via OSC 7
+const CWD_POLL_MS = 5000;
+
 function matchesSearch(commit: GitCommit, query: string): boolean {
   const q = query.toLowerCase();
   return (
@@ -75,24 +78,33 @@ export function GitPanel() {
     }
   }, [isOpen, focusedSessionId, loadGitLog]);
 
-  // Poll for cwd changes every 2 s (covers `cd`)
+  // Reload when the shell reports a new cwd (covers `cd`). Shells without
+  // the integration never report, so poll slowly until one does.
   useEffect(() => {
     if (!isOpen || !focusedSessionId) return;
 
+    const changed = (cwd: string) => {
+      if (cwd && cwd !== lastCwdRef.current) {
+        lastCwdRef.current = cwd;
+        loadGitLog();
+      }
+    };
+    const unlisten = onPtyCwdChanged(focusedSessionId, (cwd) => {
+      clearInterval(id);
+      changed(cwd);
+    });
     const poll = async () => {
       try {
-        const cwd = await ptyGetCwd(focusedSessionId);
-        if (cwd && cwd !== lastCwdRef.current) {
-          lastCwdRef.current = cwd;
-          loadGitLog();
-        }
+        changed(await ptyGetCwd(focusedSessionId));
       } catch {
         // ignore — session may not be ready yet
       }
     };
-
-    const id = setInterval(poll, 2000);
-    return () => clearInterval(id);
+    const id = setInterval(poll, CWD_POLL_MS);
+    return () => {
+      clearInterval(id);
+      unlisten.then((fn) => fn());
+    };
   }, [isOpen, focusedSessionId, loadGitLog]);
 
   const filtered = useMemo(() => {
@@ -26,6 +26,7 @@ export function TileContainer({ node }: TileContainerProps) {
         sessionId={node.sessionId}
         leafId={node.id}
         isFocused={node.id === focusedLeafId}
+        command={node.command}
       />
     );
   }
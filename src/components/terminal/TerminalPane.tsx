@@ -6,6 +6,7 @@ import { SearchBar } from "./SearchBar";
 import { useTerminalStore } from "../../stores/terminalStore";
 import { useTileStore } from "../../stores/tileStore";
 import { ptyWrite } from "../../lib/ipc";
+import type { PaneCommand } from "../../types/terminal";
 
 /** Escape a file path for pasting into a shell (backslash-escape specials). */
 function escapeShellPath(path: string): string {
@@ -16,12 +17,15 @@ interface TerminalPaneProps {
   sessionId: string;
   leafId: string;
   isFocused: boolean;
+  /** Run a program instead of a shell. */
+  command?: PaneCommand;
 }
 
 export const TerminalPane = memo(function TerminalPane({
   sessionId,
   leafId,
   isFocused,
+  command,
 }: TerminalPaneProps) {
   const addSession = useTerminalStore((s) => s.addSession);
   const removeSession = useTerminalStore((s) => s.removeSession);
@@ -48,9 +52,18 @@ export const TerminalPane = memo(function TerminalPane({
     [sessionId, updateTitle]
   );
 
+  const handleClose = useCallback(() => {
+    const destroyedSessionId = closePane(leafId);
+    if (destroyedSessionId) {
+      destroyPtySession(destroyedSessionId).catch(() => {});
+    }
+  }, [leafId, closePane]);
+
   const { attach, focus, fit } = useTerminal({
     sessionId,
+    command,
     onTitleChange,
+    onExitClose: handleClose,
   });
 
   useEffect(() => {
@@ -109,7 +122,7 @@ export const TerminalPane = memo(function TerminalPane({
           if (!isInside(e.payload.position.x, e.payload.position.y)) return;
 
           const text = e.payload.paths.map(escapeShellPath).join(" ");
-          ptyWrite(sessionId, btoa(text)).catch(() => {});
+          ptyWrite(sessionId, text).catch(() => {});
         }
       )
     );
@@ -120,13 +133,6 @@ export const TerminalPane = memo(function TerminalPane({
     };
   }, [sessionId]);
 
-  const handleClose = useCallback(() => {
-    const destroyedSessionId = closePane(leafId);
-    if (destroyedSessionId) {
-      destroyPtySession(destroyedSessionId).catch(() => {});
-    }
-  }, [leafId, closePane]);
-
   const handleSearchClose = useCallback(() => {
     closeSearch();
     requestAnimationFrame(() => focus());
@@ -3,8 +3,10 @@ import { Terminal } from "@xterm/xterm";
 import { FitAddon } from "@xterm/addon-fit";
 import { SearchAddon } from "@xterm/addon-search";
 import { WebLinksAddon } from "@xterm/addon-web-links";
-import { ptyCreate, ptyWrite, ptyResize, ptyDestroy, ptyGetCwd, listDir, onPtyOutput, onPtyExit } from "../../lib/ipc";
+import { ptyCreate, ptyAttach, ptyWrite, ptyAck, ptyResize, ptyDestroy, ptyGetCwd, listDir, onPtyExit } from "../../lib/ipc";
 import { getXtermTheme } from "../../lib/themeApplicator";
+import type { PaneCommand } from "../../types/terminal";
+import type { SavedPane } from "../../types/workspace";
 import { useThemeStore } from "../../stores/themeStore";
 import { terminalInstances } from "../../lib/terminalRegistry";
 import { useMarkdownStore } from "../../stores/markdownStore";
@@ -13,8 +15,67 @@ import { useMarkdownStore } from "../../stores/markdownStore";
 // PTYs are created once and only destroyed via destroyPtySession (explicit close).
 const activePtys = new Set<string>();
 
+// Sessions the backend kept running from before a window reload; their panes
+// attach to the existing PTY instead of creating one.
+const reattachable = new Set<string>();
+
+export function markReattachable(sessionIds: string[]) {
+  for (const id of sessionIds) reattachable.add(id);
+}
+
+// Panes recreated from the workspace saved on the last quit; their PTY starts
+// in the saved directory and profile, below the saved output.
+const restoredPanes = new Map<string, SavedPane>();
+
+export function markRestored(panes: Record<string, SavedPane>) {
+  for (const [id, pane] of Object.entries(panes)) restoredPanes.set(id, pane);
+}
+
+// Messages for panes that haven't mounted yet, e.g. startup errors
+const pendingNotices = new Map<string, string[]>();
+
+function writeNotice(term: Terminal, message: string) {
+  term.write(`\x1b[31m[${message}]\x1b[0m\r\n`);
+}
+
+/** Print an error in a pane, once it exists. */
+export function showNotice(sessionId: string, message: string) {
+  const term = terminalInstances.get(sessionId)?.term;
+  if (term) {
+    writeNotice(term, message);
+  } else {
+    pendingNotices.set(sessionId, [...(pendingNotices.get(sessionId) ?? []), message]);
+  }
+}
+
+// Flow control: the backend stops reading the PTY once too much output is
+// unacknowledged. Acks are sent once xterm.js has parsed the data, batched so
+// a fast stream doesn't turn into one IPC call per chunk.
+const ACK_BATCH = 64 * 1024;
+const pendingAcks = new Map<string, number>();
+
+function ackOutput(sessionId: string, bytes: number) {
+  const pending = (pendingAcks.get(sessionId) ?? 0) + bytes;
+  if (pending >= ACK_BATCH) {
+    pendingAcks.set(sessionId, 0);
+    ptyAck(sessionId, pending).catch(() => {});
+  } else {
+    pendingAcks.set(sessionId, pending);
+  }
+}
+
+function writeOutput(sessionId: string, bytes: Uint8Array) {
+  const term = terminalInstances.get(sessionId)?.term;
+  if (!term) {
+    ackOutput(sessionId, bytes.length);
+    return;
+  }
+  term.write(bytes, () => ackOutput(sessionId, bytes.length));
+}
+
 export function destroyPtySession(sessionId: string) {
   activePtys.delete(sessionId);
+  pendingAcks.delete(sessionId);
   const entry = terminalInstances.get(sessionId);
   if (entry) {
     entry.term.dispose();
@@ -26,16 +87,25 @@ export function destroyPtySession(sessionId: string) {
 interface UseTerminalOptions {
   sessionId: string;
   cwd?: string;
+  /** Run a program instead of a shell. */
+  command?: PaneCommand;
   onTitleChange?: (title: string) => void;
+  /** Called when a command pane with `onExit: "close"` exits. */
+  onExitClose?: () => void;
 }
 
-export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOptions) {
+export function useTerminal({ sessionId, cwd, command, onTitleChange, onExitClose }: UseTerminalOptions) {
   const termRef = useRef<Terminal | null>(null);
   const fitRef = useRef<FitAddon | null>(null);
   const containerRef = useRef<HTMLDivElement | null>(null);
 
   const onTitleChangeRef = useRef(onTitleChange);
   onTitleChangeRef.current = onTitleChange;
+  const onExitCloseRef = useRef(onExitClose);
+  onExitCloseRef.current = onExitClose;
+  // Only read when the PTY is first created
+  const commandRef = useRef(command);
+  commandRef.current = command;
 
   const attach = useCallback((element: HTMLDivElement | null) => {
     containerRef.current = element;
@@ -131,7 +201,7 @@ export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOption
                     activate() {
                       const esc = dirName.replace(/'/g, "'\\''");
                       const cmd = `cd '${esc}' && ls\n`;
-                      ptyWrite(sessionId, btoa(cmd)).catch(() => {});
+                      ptyWrite(sessionId, cmd).catch(() => {});
                     },
                   });
                 }
@@ -143,6 +213,8 @@ export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOption
       });
 
       terminalInstances.set(sessionId, { term, fitAddon, searchAddon });
+      for (const message of pendingNotices.get(sessionId) ?? []) writeNotice(term, message);
+      pendingNotices.delete(sessionId);
     }
 
     termRef.current = term;
@@ -152,7 +224,6 @@ export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOption
       onTitleChangeRef.current?.(title);
     });
 
-    let unlistenOutput: (() => void) | null = null;
     let unlistenExit: (() => void) | null = null;
     let dataDisposable: { dispose(): void } | null = null;
     let disposed = false;
@@ -162,18 +233,27 @@ export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOption
       if (disposed) return;
 
       dataDisposable = term.onData((data) => {
-        ptyWrite(sessionId, btoa(data)).catch(() => {});
+        ptyWrite(sessionId, data).catch(() => {});
       });
 
-      onPtyOutput(sessionId, (base64Data) => {
-        if (disposed) return;
-        const bytes = Uint8Array.from(atob(base64Data), (c) => c.charCodeAt(0));
-        term.write(bytes);
-      }).then((u) => { unlistenOutput = u; });
-
-      onPtyExit(sessionId, () => {
+      onPtyExit(sessionId, (exit) => {
         if (disposed) return;
-        term.write("\r\n\x1b[90m[Process exited]\x1b[0m\r\n");
+        if (exit.error) {
+          term.write(`\r\n\x1b[31m[Restart failed: ${exit.error}]\x1b[0m\r\n`);
+          return;
+        }
+        const onExit = commandRef.current?.onExit ?? "hold";
+        if (onExit === "close" && onExitCloseRef.current) {
+          onExitCloseRef.current();
+          return;
+        }
+        const reason = exit.signal_name
+          ? `terminated by ${exit.signal_name}`
+          : exit.exit_code !== null && exit.exit_code !== 0
+            ? `exited with code ${exit.exit_code}`
+            : "exited";
+        const suffix = onExit === "restart" ? ", restarting" : "";
+        term.write(`\r\n\x1b[90m[Process ${reason}${suffix}]\x1b[0m\r\n`);
       }).then((u) => { unlistenExit = u; });
     };
 
@@ -197,10 +277,28 @@ export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOption
       if (!ptyInitialized) {
         ptyInitialized = true;
 
-        if (!activePtys.has(sessionId)) {
+        if (!activePtys.has(sessionId) && reattachable.delete(sessionId)) {
+          // Session survived a window reload — replay its recent output
+          activePtys.add(sessionId);
+          ptyAttach(sessionId, (bytes) => {
+            writeOutput(sessionId, bytes);
+          }).then(() => {
+            ptyResize(sessionId, c, r).catch(() => {});
+            wireIO();
+          });
+        } else if (!activePtys.has(sessionId)) {
           // First time this session is mounted — create the PTY
           activePtys.add(sessionId);
-          ptyCreate(sessionId, c, r, cwd).then(() => {
+          const saved = restoredPanes.get(sessionId);
+          restoredPanes.delete(sessionId);
+          if (saved?.scrollback?.length) {
+            term.write(saved.scrollback.join("\r\n") + "\x1b[0m\r\n");
+          }
+          // The output channel outlives this mount, so it writes to whichever
+          // Terminal instance is registered for the session (detached or not).
+          ptyCreate(sessionId, c, r, saved?.cwd ?? cwd, saved?.profile_id ?? undefined, (bytes) => {
+            writeOutput(sessionId, bytes);
+          }, commandRef.current).then(() => {
             wireIO();
           });
         } else {
@@ -219,7 +317,6 @@ export function useTerminal({ sessionId, cwd, onTitleChange }: UseTerminalOption
     return () => {
       disposed = true;
       resizeObserver.disconnect();
-      unlistenOutput?.();
       unlistenExit?.();
       titleDisposable.dispose();
       dataDisposable?.dispose();
@@ -1,6 +1,30 @@
-import { invoke } from "@tauri-apps/api/core";
+import { Channel, invoke } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 import type { GitLogResult } from "../types/git";
+import type { Task } from "../types/task";
+import type { PromptPalette } from "../types/theme";
+import type { TileNode } from "../types/tile";
+import type { LayoutOpen, Workspace } from "../types/workspace";
+import type {
+  BlockOutput,
+  CommandBlock,
+  ExitPolicy,
+  ForegroundProcess,
+  GroupMembership,
+  LineRange,
+  MonitorAlert,
+  PaneCommand,
+  PtyExit,
+  PtyNotification,
+  PtyTeardown,
+  RecordingInfo,
+  ReplayState,
+  ScreenText,
+  SearchResults,
+  SessionInfo,
+  ShellProfile,
+  TmuxLayout,
+} from "../types/terminal";
 
 // --- PTY ---
 
@@ -8,21 +32,90 @@ export async function ptyCreate(
   sessionId: string,
   cols: number,
   rows: number,
-  cwd?: string
+  cwd: string | undefined,
+  profileId: string | undefined,
+  onOutput: (data: Uint8Array) => void,
+  command?: PaneCommand
 ): Promise<void> {
+  // Output arrives as raw bytes over a per-session channel, coalesced by the backend
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (buffer) => onOutput(new Uint8Array(buffer));
   await invoke("pty_create", {
     sessionId,
     cols,
     rows,
     cwd: cwd ?? null,
+    profileId: profileId ?? null,
+    program: command?.program ?? null,
+    args: command?.args ?? null,
+    onExit: command?.onExit ?? null,
+    onOutput: channel,
   });
 }
 
+export async function ptyListProfiles(): Promise<ShellProfile[]> {
+  return invoke("pty_list_profiles");
+}
+
+/** Sessions the backend is still running, e.g. after a window reload. */
+export async function ptyListSessions(): Promise<SessionInfo[]> {
+  return invoke("pty_list_sessions");
+}
+
+/** Why the session daemon isn't in use, or which of its sessions couldn't be taken over. */
+export async function ptyDaemonErrors(): Promise<string[]> {
+  return invoke("pty_daemon_errors");
+}
+
+/**
+ * Reconnect to a running session. Recent output is replayed through `onOutput`
+ * before live output continues.
+ */
+export async function ptyAttach(
+  sessionId: string,
+  onOutput: (data: Uint8Array) => void
+): Promise<void> {
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (buffer) => onOutput(new Uint8Array(buffer));
+  await invoke("pty_attach", { sessionId, onOutput: channel });
+}
+
+const encoder = new TextEncoder();
+
 export async function ptyWrite(
   sessionId: string,
-  data: string
+  data: string | Uint8Array
+): Promise<void> {
+  const bytes = typeof data === "string" ? encoder.encode(data) : data;
+  await invoke("pty_write", bytes, { headers: { "session-id": sessionId } });
+}
+
+export async function ptyWriteGroup(
+  group: string,
+  data: string | Uint8Array
 ): Promise<void> {
-  await invoke("pty_write", { sessionId, data });
+  const bytes = typeof data === "string" ? encoder.encode(data) : data;
+  await invoke("pty_write_group", bytes, {
+    headers: { group: encodeURIComponent(group) },
+  });
+}
+
+export async function ptyJoinGroup(
+  sessionId: string,
+  group: string
+): Promise<GroupMembership> {
+  return invoke("pty_join_group", { sessionId, group });
+}
+
+export async function ptyLeaveGroup(
+  sessionId: string,
+  group: string
+): Promise<GroupMembership> {
+  return invoke("pty_leave_group", { sessionId, group });
+}
+
+export async function ptyAck(sessionId: string, bytes: number): Promise<void> {
+  await invoke("pty_ack", { sessionId, bytes });
 }
 
 export async function ptyResize(
@@ -33,29 +126,190 @@ export async function ptyResize(
   await invoke("pty_resize", { sessionId, cols, rows });
 }
 
-export async function ptyDestroy(sessionId: string): Promise<void> {
-  await invoke("pty_destroy", { sessionId });
+export async function ptyDestroy(
+  sessionId: string,
+  graceMs?: number
+): Promise<PtyTeardown> {
+  return invoke("pty_destroy", { sessionId, graceMs: graceMs ?? null });
 }
 
 export async function ptyGetCwd(sessionId: string): Promise<string> {
   return invoke("pty_get_cwd", { sessionId });
 }
 
-export function onPtyOutput(
+export async function ptyGetForegroundProcess(
+  sessionId: string
+): Promise<ForegroundProcess | null> {
+  return invoke("pty_get_foreground_process", { sessionId });
+}
+
+export async function ptyListBlocks(sessionId: string): Promise<CommandBlock[]> {
+  return invoke("pty_list_blocks", { sessionId });
+}
+
+export async function ptyGetBlockOutput(
+  sessionId: string,
+  blockId: number
+): Promise<BlockOutput> {
+  return invoke("pty_get_block_output", { sessionId, blockId });
+}
+
+export async function ptyStartRecording(
   sessionId: string,
-  callback: (data: string) => void
+  path?: string
+): Promise<RecordingInfo> {
+  return invoke("pty_start_recording", { sessionId, path: path ?? null });
+}
+
+export async function ptyStopRecording(sessionId: string): Promise<RecordingInfo> {
+  return invoke("pty_stop_recording", { sessionId });
+}
+
+export async function ptyReplayOpen(
+  sessionId: string,
+  path: string,
+  onOutput: (data: Uint8Array) => void
+): Promise<ReplayState> {
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (buffer) => onOutput(new Uint8Array(buffer));
+  return invoke("pty_replay_open", { sessionId, path, onOutput: channel });
+}
+
+export async function ptyReplayPause(sessionId: string): Promise<ReplayState> {
+  return invoke("pty_replay_pause", { sessionId });
+}
+
+export async function ptyReplayResume(sessionId: string): Promise<ReplayState> {
+  return invoke("pty_replay_resume", { sessionId });
+}
+
+export async function ptyReplaySeek(
+  sessionId: string,
+  positionMs: number
+): Promise<ReplayState> {
+  return invoke("pty_replay_seek", { sessionId, positionMs });
+}
+
+export async function ptyReplaySetSpeed(
+  sessionId: string,
+  speed: number
+): Promise<ReplayState> {
+  return invoke("pty_replay_set_speed", { sessionId, speed });
+}
+
+export async function ptyGetScreenText(
+  sessionId: string,
+  range?: LineRange,
+  styled?: boolean
+): Promise<ScreenText> {
+  return invoke("pty_get_screen_text", {
+    sessionId,
+    range: range ?? null,
+    styled: styled ?? null,
+  });
+}
+
+export async function ptySearch(
+  sessionId: string,
+  pattern: string,
+  caseSensitive?: boolean,
+  limit?: number
+): Promise<SearchResults> {
+  return invoke("pty_search", {
+    sessionId,
+    pattern,
+    caseSensitive: caseSensitive ?? null,
+    limit: limit ?? null,
+  });
+}
+
+export function onPtyExit(
+  sessionId: string,
+  callback: (exit: PtyExit) => void
 ): Promise<UnlistenFn> {
-  return listen<string>(`pty-output-${sessionId}`, (event) => {
+  return listen<PtyExit>(`pty-exit-${sessionId}`, (event) => {
     callback(event.payload);
   });
 }
 
-export function onPtyExit(
+export function onPtyForegroundChanged(
   sessionId: string,
-  callback: () => void
+  callback: (process: ForegroundProcess | null) => void
 ): Promise<UnlistenFn> {
-  return listen<void>(`pty-exit-${sessionId}`, () => {
-    callback();
+  return listen<ForegroundProcess | null>(`pty-foreground-changed-${sessionId}`, (event) => {
+    callback(event.payload);
+  });
+}
+
+export function onPtyCwdChanged(
+  sessionId: string,
+  callback: (cwd: string) => void
+): Promise<UnlistenFn> {
+  return listen<string>(`pty-cwd-changed-${sessionId}`, (event) => {
+    callback(event.payload);
+  });
+}
+
+export function onPtyReplayState(
+  sessionId: string,
+  callback: (state: ReplayState) => void
+): Promise<UnlistenFn> {
+  return listen<ReplayState>(`pty-replay-state-${sessionId}`, (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function ptySetNotificationsMuted(
+  sessionId: string,
+  muted: boolean
+): Promise<void> {
+  await invoke("pty_set_notifications_muted", { sessionId, muted });
+}
+
+export function onPtyNotify(
+  sessionId: string,
+  callback: (notification: PtyNotification) => void
+): Promise<UnlistenFn> {
+  return listen<PtyNotification>(`pty-notify-${sessionId}`, (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function ptySetMonitor(
+  sessionId: string,
+  activityAfterMs: number | null,
+  silenceAfterMs: number | null
+): Promise<void> {
+  await invoke("pty_set_monitor", { sessionId, activityAfterMs, silenceAfterMs });
+}
+
+export function onPtyMonitor(
+  sessionId: string,
+  callback: (alert: MonitorAlert) => void
+): Promise<UnlistenFn> {
+  return listen<MonitorAlert>(`pty-monitor-${sessionId}`, (event) => {
+    callback(event.payload);
+  });
+}
+
+export async function ptySetPromptPalette(palette: PromptPalette): Promise<void> {
+  await invoke("pty_set_prompt_palette", { palette });
+}
+
+export function onPtyGroupChanged(
+  callback: (membership: GroupMembership) => void
+): Promise<UnlistenFn> {
+  return listen<GroupMembership>("pty-group-changed", (event) => {
+    callback(event.payload);
+  });
+}
+
+/** Fired when a `tmux -CC` session's panes or their layout change. */
+export function onTmuxLayout(
+  callback: (layout: TmuxLayout) => void
+): Promise<UnlistenFn> {
+  return listen<TmuxLayout>("tmux-layout", (event) => {
+    callback(event.payload);
   });
 }
 
@@ -118,3 +372,57 @@ export async function gitLocalFileDiff(
 ): Promise<import("../types/git").FileDiff> {
   return invoke("git_local_file_diff", { cwd, path });
 }
+
+// --- Tasks ---
+
+export async function taskList(cwd: string): Promise<Task[]> {
+  return invoke("task_list", { cwd });
+}
+
+/** Start a discovered task in a new session, like ptyCreate for a command pane. */
+export async function taskSpawn(
+  sessionId: string,
+  cols: number,
+  rows: number,
+  task: Task,
+  onExit: ExitPolicy | undefined,
+  onOutput: (data: Uint8Array) => void
+): Promise<void> {
+  const channel = new Channel<ArrayBuffer>();
+  channel.onmessage = (buffer) => onOutput(new Uint8Array(buffer));
+  await invoke("task_spawn", {
+    sessionId,
+    cols,
+    rows,
+    task,
+    onExit: onExit ?? null,
+    onOutput: channel,
+  });
+}
+
+// --- Workspace ---
+
+/** Save the layout with the panes' state; it's saved again on quit. */
+export async function workspaceSetLayout(layout: TileNode): Promise<void> {
+  return invoke("workspace_set_layout", { layout });
+}
+
+/** The workspace saved when the app last quit, or null. */
+export async function workspaceRestore(): Promise<Workspace | null> {
+  return invoke("workspace_restore");
+}
+
+/**
+ * Start the panes described by the nearest `.terminal-plus.toml` in `cwd` or
+ * its parents. Returns their tile tree; each pane connects with ptyAttach. A
+ * file that sets commands or env starts only once trusted: pass the `hash` of
+ * the "untrusted" result the user approved.
+ */
+export async function workspaceOpenLayout(
+  cwd: string,
+  cols: number,
+  rows: number,
+  trust?: string
+): Promise<LayoutOpen> {
+  return invoke("workspace_open_layout", { cwd, cols, rows, trust: trust ?? null });
+}
@@ -5,7 +5,8 @@ export type KeyAction =
   | "focus-next"
   | "focus-prev"
   | "toggle-git-panel"
-  | "search-terminal";
+  | "search-terminal"
+  | "open-layout";
 
 const isMac = navigator.platform.toUpperCase().includes("MAC");
 
@@ -26,6 +27,7 @@ const bindings: Keybinding[] = [
   { key: "[",   mod: true, shift: true, action: "focus-prev" },
   { key: "g",   mod: true, shift: true, action: "toggle-git-panel" },
   { key: "f",   mod: true,              action: "search-terminal" },
+  { key: "l",   mod: true, shift: true, action: "open-layout" },
 ];
 
 function matchKeybinding(e: KeyboardEvent): KeyAction | null {
@@ -74,6 +76,7 @@ export function shortcutLabel(action: KeyAction): string {
     case "focus-prev":       return `${mod}+Shift+[`;
     case "toggle-git-panel": return `${mod}+Shift+G`;
     case "search-terminal":  return `${mod}+F`;
+    case "open-layout":      return `${mod}+Shift+L`;
   }
 }
 
@@ -87,5 +90,6 @@ export function getAllShortcuts(): { label: string; shortcut: string }[] {
     { label: "Focus Previous Pane", shortcut: shortcutLabel("focus-prev") },
     { label: "Toggle Git Panel", shortcut: shortcutLabel("toggle-git-panel") },
     { label: "Search Terminal", shortcut: shortcutLabel("search-terminal") },
+    { label: "Open Project Layout", shortcut: shortcutLabel("open-layout") },
   ];
 }
@@ -1,6 +1,7 @@
-import type { Theme, ThemeColors } from "../types/theme";
+import type { PromptPalette, Theme, ThemeColors } from "../types/theme";
 import type { ITheme } from "@xterm/xterm";
 import type { GitRefType } from "../types/git";
+import { ptySetPromptPalette } from "./ipc";
 import { terminalInstances } from "./terminalRegistry";
 
 /** Map from ThemeColors key to CSS variable name */
@@ -65,6 +66,22 @@ export function getXtermTheme(colors: ThemeColors): ITheme {
   };
 }
 
+export function getPromptPalette(colors: ThemeColors): PromptPalette {
+  return {
+    user: colors.accentGreen,
+    path: colors.termBlue,
+    branch: colors.accentPurple,
+    prompt: colors.accentBlue,
+    muted: colors.termBrightBlack,
+    command: colors.accentCyan,
+    keyword: colors.accentPurple,
+    string: colors.accentGreen,
+    option: colors.accentOrange,
+    text: colors.fgPrimary,
+    error: colors.accentRed,
+  };
+}
+
 export function getGitLaneColor(colors: ThemeColors, lane: number): string {
   const arr = colors.gitLaneColors;
   return arr[lane % arr.length];
@@ -96,4 +113,7 @@ export function applyTheme(theme: Theme): void {
   for (const { term } of terminalInstances.values()) {
     term.options.theme = xtermTheme;
   }
+
+  // Regenerate the shell prompt colors; running shells pick them up at their next prompt
+  ptySetPromptPalette(getPromptPalette(colors)).catch(() => {});
 }
@@ -1,19 +1,21 @@
 import { nanoid } from "nanoid";
 import type { TileNode, TileLeaf, TileSplit, SplitDirection } from "../types/tile";
+import type { PaneCommand } from "../types/terminal";
 
-export function createLeaf(sessionId: string): TileLeaf {
-  return { type: "leaf", id: nanoid(8), sessionId };
+export function createLeaf(sessionId: string, command?: PaneCommand): TileLeaf {
+  return { type: "leaf", id: nanoid(8), sessionId, command };
 }
 
 export function splitNode(
   root: TileNode,
   targetLeafId: string,
   direction: SplitDirection,
-  newSessionId: string
+  newSessionId: string,
+  command?: PaneCommand
 ): TileNode {
   return mapNode(root, (node) => {
     if (node.type === "leaf" && node.id === targetLeafId) {
-      const newLeaf = createLeaf(newSessionId);
+      const newLeaf = createLeaf(newSessionId, command);
       const split: TileSplit = {
         type: "split",
         id: nanoid(8),
@@ -51,6 +53,16 @@ export function removeNode(
   return { ...root, children: [newFirst, newSecond] };
 }
 
+export function replaceLeaf(
+  root: TileNode,
+  targetLeafId: string,
+  replacement: TileNode
+): TileNode {
+  return mapNode(root, (node) =>
+    node.type === "leaf" && node.id === targetLeafId ? { replacement } : null
+  );
+}
+
 export function resizeSplit(
   root: TileNode,
   splitId: string,
@@ -0,0 +1,32 @@
+import type { TmuxLayout } from "../types/terminal";
+import { createLeaf, collectLeaves } from "./tileTree";
+import { useTileStore } from "../stores/tileStore";
+import { destroyPtySession, markReattachable } from "../components/terminal/useTerminal";
+
+// The sessions each `tmux -CC` gateway is shown as: its tmux panes, or the
+// gateway itself before and after control mode.
+const shown = new Map<string, string[]>();
+
+/**
+ * Show a gateway's tmux panes in place of the tiles it had, or the gateway
+ * again once control mode ends. Panes tmux closed are destroyed; the gateway
+ * keeps running behind them.
+ */
+export function applyTmuxLayout({ gateway_session_id: gateway, layout }: TmuxLayout) {
+  const previous = shown.get(gateway) ?? [gateway];
+  const next = layout ?? createLeaf(gateway);
+  const sessionIds = collectLeaves(next).map((l) => l.sessionId);
+
+  // The backend registered the tmux panes already; their terminals attach
+  markReattachable(sessionIds.filter((id) => id !== gateway));
+  useTileStore.getState().swapSessions(previous, next);
+
+  for (const id of previous) {
+    if (id !== gateway && !sessionIds.includes(id)) destroyPtySession(id).catch(() => {});
+  }
+  if (layout) {
+    shown.set(gateway, sessionIds);
+  } else {
+    shown.delete(gateway);
+  }
+}
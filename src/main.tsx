@@ -2,9 +2,78 @@ import React from "react";
 import ReactDOM from "react-dom/client";
 import "@xterm/xterm/css/xterm.css";
 import App from "./App";
+import {
+  onTmuxLayout,
+  ptyDaemonErrors,
+  ptyListSessions,
+  workspaceRestore,
+  workspaceSetLayout,
+} from "./lib/ipc";
+import { applyTmuxLayout } from "./lib/tmuxTiles";
+import { useTileStore } from "./stores/tileStore";
+import { markReattachable, markRestored, showNotice } from "./components/terminal/useTerminal";
+import { collectLeaves } from "./lib/tileTree";
+import type { TileNode } from "./types/tile";
 
-ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
-  <React.StrictMode>
-    <App />
-  </React.StrictMode>
-);
+async function initialLayout() {
+  // After a window reload the backend still runs the old shells; show them
+  // again instead of starting fresh ones.
+  const sessions = await ptyListSessions().catch(() => []);
+  if (sessions.length > 0) {
+    const ids = sessions.map((s) => s.session_id);
+    markReattachable(ids);
+    useTileStore.getState().adoptSessions(ids);
+    return;
+  }
+  // Otherwise recreate the panes open when the app last quit
+  const workspace = await workspaceRestore().catch(() => null);
+  if (workspace) {
+    markRestored(workspace.panes);
+    useTileStore.getState().restoreLayout(workspace.layout);
+  }
+}
+
+initialLayout().finally(() => {
+  // Problems with the session daemon show up in the first pane
+  ptyDaemonErrors()
+    .then((errors) => {
+      const first = collectLeaves(useTileStore.getState().root)[0];
+      if (first) for (const error of errors) showNotice(first.sessionId, error);
+    })
+    .catch(() => {});
+
+  // Sessions that start `tmux -CC` are shown as their tmux panes
+  onTmuxLayout(applyTmuxLayout).catch(() => {});
+
+  // The layout is saved once it settles (a resize drag changes it every
+  // frame), and again with the panes' cwd and scrollback on quit. A failure
+  // is shown once in the first pane, until a save succeeds again.
+  let saveError: string | null = null;
+  let saveTimer: ReturnType<typeof setTimeout> | undefined;
+  const saveLayout = (root: TileNode) => {
+    clearTimeout(saveTimer);
+    saveTimer = setTimeout(() => writeLayout(root), 500);
+  };
+  const writeLayout = (root: TileNode) => {
+    workspaceSetLayout(root)
+      .then(() => {
+        saveError = null;
+      })
+      .catch((e) => {
+        const message = `Failed to save the workspace: ${e}`;
+        const first = collectLeaves(root)[0];
+        if (first && message !== saveError) showNotice(first.sessionId, message);
+        saveError = message;
+      });
+  };
+  saveLayout(useTileStore.getState().root);
+  useTileStore.subscribe((state, prev) => {
+    if (state.root !== prev.root) saveLayout(state.root);
+  });
+
+  ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
+    <React.StrictMode>
+      <App />
+    </React.StrictMode>
+  );
+});
@@ -1,6 +1,7 @@
 import { create } from "zustand";
 import { nanoid } from "nanoid";
-import type { TileNode, TileLeaf, SplitDirection } from "../types/tile";
+import type { TileNode, TileLeaf, TileSplit, SplitDirection } from "../types/tile";
+import type { PaneCommand } from "../types/terminal";
 import {
   createLeaf,
   splitNode,
@@ -8,6 +9,7 @@ import {
   resizeSplit,
   collectLeaves,
   getAdjacentLeaf,
+  replaceLeaf,
 } from "../lib/tileTree";
 
 interface TileState {
@@ -15,12 +17,24 @@ interface TileState {
   focusedLeafId: string;
   initialSessionId: string;
 
-  split: (direction: SplitDirection, newSessionId: string) => string | null;
+  /** Split the focused pane; with `command`, the new pane runs it instead of a shell. */
+  split: (direction: SplitDirection, newSessionId: string, command?: PaneCommand) => string | null;
   closePane: (leafId: string) => string | null;
   resize: (splitId: string, ratio: number) => void;
   focusNext: () => void;
   focusPrev: () => void;
   setFocus: (leafId: string) => void;
+  /** Replace the layout with one pane per existing session, e.g. after a reload. */
+  adoptSessions: (sessionIds: string[]) => void;
+  /** Replace the layout with a saved one, focusing its first pane. */
+  restoreLayout: (root: TileNode) => void;
+  /** Split the leaf, putting `layout` beside it and focusing its first pane. */
+  splitWithLayout: (leafId: string, layout: TileNode) => boolean;
+  /**
+   * Put `layout` where the first pane showing one of `sessionIds` is and drop
+   * the others. Returns false if none of them is shown.
+   */
+  swapSessions: (sessionIds: string[], layout: TileNode) => boolean;
 }
 
 function makeInitialState() {
@@ -36,9 +50,9 @@ export const useTileStore = create<TileState>()((set, get) => ({
   focusedLeafId: initial.focusedLeafId,
   initialSessionId: initial.initialSessionId,
 
-  split: (direction, newSessionId) => {
+  split: (direction, newSessionId, command) => {
     const { root, focusedLeafId } = get();
-    const newRoot = splitNode(root, focusedLeafId, direction, newSessionId);
+    const newRoot = splitNode(root, focusedLeafId, direction, newSessionId, command);
     if (newRoot === root) return null;
 
     const leaves = collectLeaves(newRoot);
@@ -88,4 +102,60 @@ export const useTileStore = create<TileState>()((set, get) => ({
   setFocus: (leafId) => {
     set({ focusedLeafId: leafId });
   },
+
+  adoptSessions: (sessionIds) => {
+    if (sessionIds.length === 0) return;
+    const first = createLeaf(sessionIds[0]);
+    let root: TileNode = first;
+    let lastLeafId = first.id;
+    // Each new pane splits the previous one, alternating direction
+    sessionIds.slice(1).forEach((sessionId, i) => {
+      const direction: SplitDirection = i % 2 === 0 ? "horizontal" : "vertical";
+      root = splitNode(root, lastLeafId, direction, sessionId);
+      const leaf = collectLeaves(root).find((l) => l.sessionId === sessionId);
+      if (leaf) lastLeafId = leaf.id;
+    });
+    set({ root, focusedLeafId: first.id, initialSessionId: sessionIds[0] });
+  },
+
+  restoreLayout: (root) => {
+    const first = collectLeaves(root)[0];
+    if (!first) return;
+    set({ root, focusedLeafId: first.id, initialSessionId: first.sessionId });
+  },
+
+  splitWithLayout: (leafId, layout) => {
+    const { root } = get();
+    const leaf = collectLeaves(root).find((l) => l.id === leafId);
+    const first = collectLeaves(layout)[0];
+    if (!leaf || !first) return false;
+
+    const split: TileSplit = {
+      type: "split",
+      id: nanoid(8),
+      direction: "vertical",
+      ratio: 0.5,
+      children: [leaf, layout],
+    };
+    set({ root: replaceLeaf(root, leafId, split), focusedLeafId: first.id });
+    return true;
+  },
+
+  swapSessions: (sessionIds, layout) => {
+    const { root, focusedLeafId } = get();
+    const [target, ...rest] = collectLeaves(root).filter((l) =>
+      sessionIds.includes(l.sessionId)
+    );
+    const first = collectLeaves(layout)[0];
+    if (!target || !first) return false;
+
+    // Drop the others first: an unchanged layout reuses their leaf ids
+    let newRoot = root;
+    for (const leaf of rest) newRoot = removeNode(newRoot, leaf.id) ?? newRoot;
+    newRoot = replaceLeaf(newRoot, target.id, layout);
+
+    const focusKept = collectLeaves(newRoot).some((l) => l.id === focusedLeafId);
+    set({ root: newRoot, focusedLeafId: focusKept ? focusedLeafId : first.id });
+    return true;
+  },
 }));
@@ -0,0 +1,10 @@
+export type TaskSource = "npm" | "cargo" | "make" | "just" | "python";
+
+export interface Task {
+  name: string;
+  source: TaskSource;
+  detail: string | null;
+  program: string;
+  args: string[];
+  cwd: string;
+}
@@ -1,5 +1,160 @@
+import type { TileNode } from "./tile";
+
 export interface TerminalSession {
   id: string;
   title: string;
   cwd: string;
 }
+
+export type IntegrationLevel = "full" | "marks_only" | "off";
+
+export interface ShellProfile {
+  id: string;
+  name: string;
+  program: string | null;
+  args: string[];
+  env: Record<string, string>;
+  cwd: string | null;
+  /** Null: login only for known shells. */
+  login: boolean | null;
+  integration: IntegrationLevel;
+}
+
+export interface PtyExit {
+  exit_code: number | null;
+  signal: number | null;
+  signal_name: string | null;
+  runtime_ms: number;
+  /** Why a restart failed; the session is gone. */
+  error: string | null;
+}
+
+export type ExitPolicy = "close" | "hold" | "restart";
+
+/** A program run directly in a pane instead of a shell (e.g. `htop`). */
+export interface PaneCommand {
+  program: string;
+  args?: string[];
+  onExit?: ExitPolicy;
+}
+
+/** A session still running in the backend, from `ptyListSessions`. */
+export interface SessionInfo {
+  session_id: string;
+  pid: number | null;
+  cwd: string;
+  profile_id: string;
+  /** Unix time in milliseconds. */
+  started_at: number;
+  exit: PtyExit | null;
+}
+
+export interface PtyTeardown {
+  outcome: "already_exited" | "hangup" | "killed" | "closed";
+  exit: PtyExit | null;
+  elapsed_ms: number;
+}
+
+export interface ForegroundProcess {
+  pid: number;
+  name: string;
+  cmdline: string[];
+  is_shell: boolean;
+}
+
+export interface CommandBlock {
+  id: number;
+  command: string | null;
+  cwd: string | null;
+  started_at: number;
+  finished_at: number | null;
+  exit_code: number | null;
+  output_start: number;
+  output_end: number | null;
+}
+
+export interface BlockOutput {
+  block: CommandBlock;
+  text: string;
+  truncated: boolean;
+}
+
+export interface RecordingInfo {
+  path: string;
+  started_at: number;
+  duration_ms: number;
+}
+
+export interface ReplayState {
+  position_ms: number;
+  duration_ms: number;
+  speed: number;
+  paused: boolean;
+  finished: boolean;
+  cols: number;
+  rows: number;
+}
+
+export interface LineRange {
+  start: number;
+  end: number;
+}
+
+export interface ScreenText {
+  lines: string[];
+  start: number;
+  total_lines: number;
+  scrollback_lines: number;
+  cols: number;
+  rows: number;
+  cursor_row: number;
+  cursor_col: number;
+}
+
+export interface MatchRange {
+  start: number;
+  end: number;
+}
+
+export interface SearchMatch {
+  line: number;
+  text: string;
+  ranges: MatchRange[];
+  context_before: string[];
+  context_after: string[];
+}
+
+export interface SearchResults {
+  matches: SearchMatch[];
+  total_matches: number;
+  first_line: number;
+  screen_first_line: number;
+}
+
+export type NotificationKind = "bell" | "osc9" | "osc777";
+
+export interface PtyNotification {
+  kind: NotificationKind;
+  title: string | null;
+  body: string | null;
+}
+
+export type MonitorKind = "activity" | "silence";
+
+export interface MonitorAlert {
+  kind: MonitorKind;
+  quiet_ms: number;
+}
+
+export interface GroupMembership {
+  group: string;
+  members: string[];
+}
+
+/** Payload of `tmux-layout`: the panes of a `tmux -CC` session, as tiles. */
+export interface TmuxLayout {
+  /** The session running `tmux -CC`. */
+  gateway_session_id: string;
+  /** Null once control mode has ended and the gateway's own pane returns. */
+  layout: TileNode | null;
+}
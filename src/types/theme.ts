@@ -71,6 +71,21 @@ export interface ThemeColors {
   fileStatusRename: string;
 }
 
+/** Shell prompt and syntax highlighting colors, derived from the theme */
+export interface PromptPalette {
+  user: string;
+  path: string;
+  branch: string;
+  prompt: string;
+  muted: string;
+  command: string;
+  keyword: string;
+  string: string;
+  option: string;
+  text: string;
+  error: string;
+}
+
 export interface Theme {
   id: string;
   name: string;
@@ -1,3 +1,5 @@
+import type { PaneCommand } from "./terminal";
+
 export type SplitDirection = "horizontal" | "vertical";
 
 export interface TileSplit {
@@ -12,6 +14,8 @@ export interface TileLeaf {
   type: "leaf";
   id: string;
   sessionId: string;
+  /** Set for command panes; read when the pane's session is created. */
+  command?: PaneCommand;
 }
 
 export type TileNode = TileSplit | TileLeaf;
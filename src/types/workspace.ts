@@ -0,0 +1,25 @@
+import type { TileNode } from "./tile";
+
+/** A pane as it was when the app last quit. */
+export interface SavedPane {
+  cwd: string | null;
+  /** Null for the default profile and for command panes. */
+  profile_id: string | null;
+  /** Recent output with SGR sequences; absent unless enabled in settings. */
+  scrollback?: string[];
+}
+
+export interface Workspace {
+  version: number;
+  layout: TileNode;
+  /** Keyed by session id. */
+  panes: Record<string, SavedPane>;
+}
+
+/**
+ * What opening a project layout did. An untrusted file started nothing; open
+ * it again with its `hash` once the user has approved what it runs.
+ */
+export type LayoutOpen =
+  | { status: "opened"; layout: TileNode }
+  | { status: "untrusted"; path: string; hash: string; commands: string[]; env: string[] };